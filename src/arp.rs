@@ -0,0 +1,214 @@
+#[cfg(target_os = "linux")]
+pub use linux::ArpScanner;
+
+#[cfg(not(target_os = "linux"))]
+pub use unsupported::ArpScanner;
+
+#[cfg(not(target_os = "linux"))]
+mod unsupported {
+    use std::net::IpAddr;
+    use anyhow::Result;
+    use crate::timing::Timing;
+    use crate::types::MacAddress;
+
+    pub struct ArpScanner;
+
+    impl ArpScanner {
+        pub fn new() -> Result<Self> {
+            Err(anyhow::anyhow!("ARP discovery is only supported on Linux"))
+        }
+
+        pub fn supports(&self, _ip: IpAddr) -> bool {
+            false
+        }
+
+        pub async fn resolve(&self, _ip: IpAddr, _timing: &Timing) -> Result<Option<MacAddress>> {
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::collections::HashMap;
+    use std::ffi::CStr;
+    use std::io;
+    use std::mem;
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::sync::Arc;
+    use anyhow::Result;
+    use ipnetwork::Ipv4Network;
+    use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+    use crate::raw_socket::Responder;
+    use crate::timing::Timing;
+    use crate::types::MacAddress;
+
+    const ARP_HTYPE_ETHERNET: u16 = 1;
+    const ARP_PTYPE_IPV4: u16 = 0x0800;
+    const ARP_OP_REQUEST: u16 = 1;
+    const ARP_OP_REPLY: u16 = 2;
+    const ARP_PACKET_LEN: usize = 28;
+    const BROADCAST: [u8; 6] = [0xff; 6];
+
+    #[derive(Debug, Clone)]
+    struct Interface {
+        index: i32,
+        mac: MacAddress,
+        network: Ipv4Network,
+    }
+
+    pub struct ArpScanner {
+        socket: Arc<Socket>,
+        interfaces: Vec<Interface>,
+        responder: Responder<Ipv4Addr, MacAddress>,
+    }
+
+    impl ArpScanner {
+        pub fn new() -> Result<Self> {
+            let interfaces = local_interfaces()?;
+            if interfaces.is_empty() {
+                return Err(anyhow::anyhow!("No Ethernet interfaces with an IPv4 address"));
+            }
+
+            let protocol = Protocol::from((libc::ETH_P_ARP as u16).to_be() as i32);
+            let socket = Socket::new(Domain::PACKET, Type::DGRAM, Some(protocol)).map_err(|e| {
+                anyhow::anyhow!("Failed to open packet socket (requires root or CAP_NET_RAW): {}", e)
+            })?;
+            let socket = Arc::new(socket);
+
+            let responder = Responder::default();
+            responder.spawn_receiver(socket.clone(), |packet, _| parse_reply(packet))?;
+
+            Ok(Self {
+                socket,
+                interfaces,
+                responder,
+            })
+        }
+
+        pub fn supports(&self, ip: IpAddr) -> bool {
+            match ip {
+                IpAddr::V4(ip) => self.interface_for(ip).is_some(),
+                IpAddr::V6(_) => false,
+            }
+        }
+
+        pub async fn resolve(&self, ip: IpAddr, timing: &Timing) -> Result<Option<MacAddress>> {
+            let IpAddr::V4(target) = ip else {
+                return Ok(None);
+            };
+            let interface = self.interface_for(target)
+                .ok_or_else(|| anyhow::anyhow!("{} is not on a local Ethernet segment", ip))?;
+            let packet = build_request(interface, target);
+            let dest = link_addr(interface.index, BROADCAST);
+
+            self.responder
+                .request(target, ip, timing, || self.socket.send_to(&packet, &dest))
+                .await
+        }
+
+        fn interface_for(&self, ip: Ipv4Addr) -> Option<&Interface> {
+            self.interfaces
+                .iter()
+                .find(|interface| interface.network.contains(ip) && interface.network.ip() != ip)
+        }
+    }
+
+    fn parse_reply(packet: &[u8]) -> Option<(Ipv4Addr, MacAddress)> {
+        if packet.len() < ARP_PACKET_LEN
+            || u16::from_be_bytes([packet[0], packet[1]]) != ARP_HTYPE_ETHERNET
+            || u16::from_be_bytes([packet[2], packet[3]]) != ARP_PTYPE_IPV4
+            || packet[4] != 6
+            || packet[5] != 4
+            || u16::from_be_bytes([packet[6], packet[7]]) != ARP_OP_REPLY
+        {
+            return None;
+        }
+        let sender_mac = MacAddress(packet[8..14].try_into().ok()?);
+        let sender_ip = Ipv4Addr::new(packet[14], packet[15], packet[16], packet[17]);
+        Some((sender_ip, sender_mac))
+    }
+
+    fn build_request(interface: &Interface, target: Ipv4Addr) -> Vec<u8> {
+        let mut packet = Vec::with_capacity(ARP_PACKET_LEN);
+        packet.extend_from_slice(&ARP_HTYPE_ETHERNET.to_be_bytes());
+        packet.extend_from_slice(&ARP_PTYPE_IPV4.to_be_bytes());
+        packet.extend_from_slice(&[6, 4]);
+        packet.extend_from_slice(&ARP_OP_REQUEST.to_be_bytes());
+        packet.extend_from_slice(&interface.mac.0);
+        packet.extend_from_slice(&interface.network.ip().octets());
+        packet.extend_from_slice(&[0; 6]);
+        packet.extend_from_slice(&target.octets());
+        packet
+    }
+
+    fn link_addr(index: i32, mac: [u8; 6]) -> SockAddr {
+        // SAFETY: sockaddr_ll fits in sockaddr_storage and is fully initialised below.
+        unsafe {
+            let mut storage: libc::sockaddr_storage = mem::zeroed();
+            let addr = &mut *(&mut storage as *mut libc::sockaddr_storage as *mut libc::sockaddr_ll);
+            addr.sll_family = libc::AF_PACKET as u16;
+            addr.sll_protocol = (libc::ETH_P_ARP as u16).to_be();
+            addr.sll_ifindex = index;
+            addr.sll_halen = 6;
+            addr.sll_addr[..6].copy_from_slice(&mac);
+            SockAddr::new(storage, mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t)
+        }
+    }
+
+    fn local_interfaces() -> Result<Vec<Interface>> {
+        let mut addrs: *mut libc::ifaddrs = std::ptr::null_mut();
+        // SAFETY: getifaddrs fills `addrs` with a list that is released by freeifaddrs below.
+        if unsafe { libc::getifaddrs(&mut addrs) } != 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+
+        let mut networks = Vec::new();
+        let mut links = HashMap::new();
+        let mut cursor = addrs;
+        while !cursor.is_null() {
+            // SAFETY: `cursor` points into the list returned by getifaddrs.
+            let ifa = unsafe { &*cursor };
+            cursor = ifa.ifa_next;
+            if ifa.ifa_addr.is_null() || ifa.ifa_flags & libc::IFF_LOOPBACK as u32 != 0 {
+                continue;
+            }
+            // SAFETY: ifa_name is a NUL-terminated string owned by the list.
+            let name = unsafe { CStr::from_ptr(ifa.ifa_name) }.to_string_lossy().into_owned();
+
+            // SAFETY: the address family determines the concrete sockaddr type.
+            match unsafe { (*ifa.ifa_addr).sa_family } as i32 {
+                libc::AF_INET if !ifa.ifa_netmask.is_null() => {
+                    let (addr, mask) = unsafe {
+                        (
+                            &*(ifa.ifa_addr as *const libc::sockaddr_in),
+                            &*(ifa.ifa_netmask as *const libc::sockaddr_in),
+                        )
+                    };
+                    let addr = Ipv4Addr::from(u32::from_be(addr.sin_addr.s_addr));
+                    let mask = Ipv4Addr::from(u32::from_be(mask.sin_addr.s_addr));
+                    if let Ok(network) = Ipv4Network::with_netmask(addr, mask) {
+                        networks.push((name, network));
+                    }
+                }
+                libc::AF_PACKET => {
+                    let link = unsafe { &*(ifa.ifa_addr as *const libc::sockaddr_ll) };
+                    if link.sll_halen == 6 && link.sll_addr[..6] != [0; 6] {
+                        let mac = MacAddress(link.sll_addr[..6].try_into().unwrap());
+                        links.insert(name, (link.sll_ifindex, mac));
+                    }
+                }
+                _ => {}
+            }
+        }
+        // SAFETY: `addrs` was returned by getifaddrs and is not used afterwards.
+        unsafe { libc::freeifaddrs(addrs) };
+
+        Ok(networks
+            .into_iter()
+            .filter_map(|(name, network)| {
+                links.get(&name).map(|&(index, mac)| Interface { index, mac, network })
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,144 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CongestionStats {
+    pub min_parallelism: usize,
+    pub max_parallelism: usize,
+    pub final_parallelism: usize,
+    pub peak_parallelism: usize,
+    pub backoffs: u64,
+    pub retransmitted: u64,
+    pub unanswered: u64,
+}
+
+#[derive(Debug)]
+struct Window {
+    size: f64,
+    threshold: f64,
+    in_flight: usize,
+    peak: usize,
+    last_backoff: Option<Instant>,
+    backoffs: u64,
+    retransmitted: u64,
+    unanswered: u64,
+}
+
+#[derive(Debug)]
+pub struct CongestionController {
+    min: usize,
+    max: usize,
+    window: Mutex<Window>,
+    notify: Notify,
+}
+
+pub struct Slot<'a> {
+    controller: &'a CongestionController,
+}
+
+impl CongestionController {
+    pub fn new(min: usize, max: usize) -> Self {
+        let min = min.max(1);
+        let max = max.max(min);
+        Self {
+            min,
+            max,
+            window: Mutex::new(Window {
+                size: min as f64,
+                threshold: max as f64,
+                in_flight: 0,
+                peak: min,
+                last_backoff: None,
+                backoffs: 0,
+                retransmitted: 0,
+                unanswered: 0,
+            }),
+            notify: Notify::new(),
+        }
+    }
+
+    pub async fn acquire(&self) -> Slot<'_> {
+        loop {
+            let notified = self.notify.notified();
+            let mut notified = std::pin::pin!(notified);
+            notified.as_mut().enable();
+
+            {
+                let mut window = self.window.lock().unwrap();
+                if window.in_flight < window.size as usize {
+                    window.in_flight += 1;
+                    return Slot { controller: self };
+                }
+            }
+
+            notified.await;
+        }
+    }
+
+    pub fn on_success(&self) {
+        let mut window = self.window.lock().unwrap();
+        let before = window.size as usize;
+        window.size = if window.size < window.threshold {
+            window.size + 1.0
+        } else {
+            window.size + 1.0 / window.size
+        }
+        .min(self.max as f64);
+
+        let after = window.size as usize;
+        window.peak = window.peak.max(after);
+        drop(window);
+
+        for _ in before..after {
+            self.notify.notify_one();
+        }
+    }
+
+    pub fn on_loss(&self, rtt: Duration) {
+        let mut window = self.window.lock().unwrap();
+        window.retransmitted += 1;
+        self.back_off(&mut window, rtt);
+    }
+
+    pub fn on_timeout(&self, rtt: Duration) {
+        let mut window = self.window.lock().unwrap();
+        window.unanswered += 1;
+        self.back_off(&mut window, rtt);
+    }
+
+    pub fn stats(&self) -> CongestionStats {
+        let window = self.window.lock().unwrap();
+        CongestionStats {
+            min_parallelism: self.min,
+            max_parallelism: self.max,
+            final_parallelism: window.size as usize,
+            peak_parallelism: window.peak,
+            backoffs: window.backoffs,
+            retransmitted: window.retransmitted,
+            unanswered: window.unanswered,
+        }
+    }
+
+    fn back_off(&self, window: &mut Window, rtt: Duration) {
+        if window.last_backoff.is_some_and(|last| last.elapsed() < rtt) {
+            return;
+        }
+
+        window.threshold = (window.size / 2.0).max(self.min as f64);
+        window.size = window.threshold;
+        window.last_backoff = Some(Instant::now());
+        window.backoffs += 1;
+    }
+
+    fn release(&self) {
+        self.window.lock().unwrap().in_flight -= 1;
+        self.notify.notify_one();
+    }
+}
+
+impl Drop for Slot<'_> {
+    fn drop(&mut self) {
+        self.controller.release();
+    }
+}
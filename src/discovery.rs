@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use anyhow::{Context, Result};
+use futures::FutureExt;
+use futures::stream::{FuturesUnordered, StreamExt};
+use crate::arp::ArpScanner;
+use crate::icmp::IcmpPinger;
+use crate::mac::MacVendors;
+use crate::scanner::try_connect;
+use crate::timing::Timing;
+use crate::types::{MacAddress, PortState, Target};
+
+const NEIGHBOUR_TABLE_PATH: &str = "/proc/net/arp";
+const ARP_FLAG_COMPLETE: u32 = 0x2;
+const NEIGHBOUR_TABLE_TTL: Duration = Duration::from_secs(1);
+
+#[derive(Default)]
+struct NeighbourTable {
+    cached: Mutex<Option<(Instant, HashMap<IpAddr, MacAddress>)>>,
+}
+
+#[derive(Clone)]
+pub struct HostDiscovery {
+    tcp_ports: Vec<u16>,
+    icmp: Option<Arc<IcmpPinger>>,
+    arp: Option<Arc<ArpScanner>>,
+    mac_vendors: Arc<MacVendors>,
+    neighbours: Arc<NeighbourTable>,
+}
+
+impl HostDiscovery {
+    pub fn new(tcp_ports: Vec<u16>) -> Self {
+        Self {
+            tcp_ports,
+            icmp: None,
+            arp: None,
+            mac_vendors: Arc::new(MacVendors::default()),
+            neighbours: Arc::new(NeighbourTable::default()),
+        }
+    }
+
+    pub fn with_icmp(mut self, icmp: IcmpPinger) -> Self {
+        self.icmp = Some(Arc::new(icmp));
+        self
+    }
+
+    pub fn with_arp(mut self, arp: ArpScanner) -> Self {
+        self.arp = Some(Arc::new(arp));
+        self
+    }
+
+    pub fn with_mac_vendors(mut self, mac_vendors: MacVendors) -> Self {
+        self.mac_vendors = Arc::new(mac_vendors);
+        self
+    }
+
+    pub fn probes_per_host(&self) -> usize {
+        self.tcp_ports.len() + usize::from(self.icmp.is_some()) + usize::from(self.arp.is_some())
+    }
+
+    pub async fn discover(&self, mut target: Target, timing: &Timing) -> (Target, Result<bool>) {
+        let (alive, mac) = match self.probe(&target, timing).await {
+            Ok(probed) => probed,
+            Err(e) => return (target, Err(e)),
+        };
+        if let Some(mac) = mac {
+            target.vendor = self.mac_vendors.lookup(&mac).map(str::to_string);
+            target.mac = Some(mac);
+        }
+        (target, Ok(alive))
+    }
+
+    async fn probe(&self, target: &Target, timing: &Timing) -> Result<(bool, Option<MacAddress>)> {
+        let ip = target.ip;
+        if let Some(arp) = self.arp.as_ref().filter(|arp| arp.supports(ip)) {
+            let mac = arp.resolve(ip, timing).await
+                .with_context(|| format!("ARP discovery of {} failed", ip))?;
+            return Ok((mac.is_some(), mac));
+        }
+
+        let mut pings: FuturesUnordered<_> = self.tcp_ports
+            .iter()
+            .map(|&port| async move {
+                let (state, _) = try_connect(target.socket_addr(port), timing).await?;
+                Ok(matches!(state, PortState::Open | PortState::Closed))
+            }.boxed())
+            .collect();
+
+        if let Some(icmp) = self.icmp.as_ref().filter(|icmp| icmp.supports(ip)) {
+            pings.push(async move {
+                icmp.ping(target.socket_addr(0), timing).await
+                    .with_context(|| format!("ICMP ping of {} failed", ip))
+            }.boxed());
+        }
+
+        let mut error = None;
+        while let Some(alive) = pings.next().await {
+            match alive {
+                Ok(true) => return Ok((true, self.neighbours.lookup(ip))),
+                Ok(false) => {}
+                Err(e) => {
+                    error.get_or_insert(e);
+                }
+            }
+        }
+
+        match (self.neighbours.lookup(ip), error) {
+            (Some(mac), _) => Ok((true, Some(mac))),
+            (None, Some(e)) => Err(e),
+            (None, None) => Ok((false, None)),
+        }
+    }
+}
+
+impl NeighbourTable {
+    fn lookup(&self, ip: IpAddr) -> Option<MacAddress> {
+        let mut cached = self.cached.lock().unwrap();
+        let stale = cached
+            .as_ref()
+            .is_none_or(|(loaded, _)| loaded.elapsed() > NEIGHBOUR_TABLE_TTL);
+        if stale {
+            *cached = Some((Instant::now(), read_neighbour_table()));
+        }
+        cached.as_ref().and_then(|(_, table)| table.get(&ip).copied())
+    }
+}
+
+fn read_neighbour_table() -> HashMap<IpAddr, MacAddress> {
+    let Ok(table) = std::fs::read_to_string(NEIGHBOUR_TABLE_PATH) else {
+        return HashMap::new();
+    };
+
+    table
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 4 {
+                return None;
+            }
+            let ip: IpAddr = fields[0].parse().ok()?;
+            let flags = u32::from_str_radix(fields[2].trim_start_matches("0x"), 16).ok()?;
+            let mac: MacAddress = fields[3].parse().ok()?;
+            (flags & ARP_FLAG_COMPLETE != 0 && mac.0 != [0; 6]).then_some((ip, mac))
+        })
+        .collect()
+}
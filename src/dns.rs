@@ -1,11 +1,12 @@
-use anyhow::{Result, Context};
+use anyhow::Result;
 use std::net::IpAddr;
 use tokio::net::lookup_host;
 
 pub async fn resolve_domain(domain: &str) -> Result<Vec<IpAddr>> {
-    match lookup_host(domain).await {
+    match lookup_host((domain, 0)).await {
         Ok(ips) => {
-            let ips: Vec<IpAddr> = ips.map(|s| s.ip()).collect();
+            let mut ips: Vec<IpAddr> = ips.map(|s| s.ip()).collect();
+            ips.dedup();
             if ips.is_empty() {
                 Err(anyhow::anyhow!("No IP addresses found for domain: {}", domain))
             } else {
@@ -0,0 +1,55 @@
+use std::fs;
+use std::net::IpAddr;
+use anyhow::{Result, Context};
+use crate::targets::TargetSpec;
+
+#[derive(Debug, Clone, Default)]
+pub struct ExcludeList {
+    specs: Vec<TargetSpec>,
+}
+
+impl ExcludeList {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut list = Self::default();
+        list.add_spec(spec)?;
+        Ok(list)
+    }
+
+    pub fn load(file_path: &str) -> Result<Self> {
+        let content = fs::read_to_string(file_path)
+            .with_context(|| format!("Failed to read exclude file {}", file_path))?;
+        let mut list = Self::default();
+
+        for (number, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("");
+            list.add_spec(line)
+                .with_context(|| format!("{}:{}", file_path, number + 1))?;
+        }
+
+        Ok(list)
+    }
+
+    pub fn extend(&mut self, other: ExcludeList) {
+        self.specs.extend(other.specs);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.specs.is_empty()
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.specs.iter().any(|spec| spec.contains(ip))
+    }
+
+    fn add_spec(&mut self, spec: &str) -> Result<()> {
+        for item in spec.split(|c: char| c.is_whitespace() || c == ',').filter(|i| !i.is_empty()) {
+            match TargetSpec::parse(item, true)? {
+                TargetSpec::Hostname(_) => {
+                    return Err(anyhow::anyhow!("Invalid exclude entry: {}", item));
+                }
+                target_spec => self.specs.push(target_spec),
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::Arc;
+use anyhow::Result;
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+use crate::raw_socket::Responder;
+use crate::timing::Timing;
+
+const ICMP_ECHO_REQUEST: u8 = 8;
+const ICMP_ECHO_REPLY: u8 = 0;
+const ICMPV6_ECHO_REQUEST: u8 = 128;
+const ICMPV6_ECHO_REPLY: u8 = 129;
+const ECHO_PAYLOAD: &[u8] = b"rustcan-ping";
+
+pub struct IcmpPinger {
+    socket_v4: Arc<Socket>,
+    socket_v6: Option<Arc<Socket>>,
+    identifier: u16,
+    sequence: AtomicU16,
+    responder: Responder<(IpAddr, u16), ()>,
+}
+
+impl IcmpPinger {
+    pub fn new() -> Result<Self> {
+        let socket_v4 = Arc::new(Socket::new(Domain::IPV4, Type::RAW, Some(Protocol::ICMPV4)).map_err(|e| {
+            anyhow::anyhow!("Failed to open ICMP socket (requires root or CAP_NET_RAW): {}", e)
+        })?);
+        let socket_v6 = Socket::new(Domain::IPV6, Type::RAW, Some(Protocol::ICMPV6)).ok().map(Arc::new);
+
+        let identifier = rand::random::<u16>();
+        let responder = Responder::default();
+
+        responder.spawn_receiver(socket_v4.clone(), move |packet, _| {
+            echo_reply(parse_ipv4_reply(packet)?, identifier)
+        })?;
+        if let Some(socket_v6) = &socket_v6 {
+            responder.spawn_receiver(socket_v6.clone(), move |packet, from| {
+                let reply = parse_echo_reply(from.as_socket()?.ip(), packet, ICMPV6_ECHO_REPLY)?;
+                echo_reply(reply, identifier)
+            })?;
+        }
+
+        Ok(Self {
+            socket_v4,
+            socket_v6,
+            identifier,
+            sequence: AtomicU16::new(0),
+            responder,
+        })
+    }
+
+    pub fn supports(&self, ip: IpAddr) -> bool {
+        ip.is_ipv4() || self.socket_v6.is_some()
+    }
+
+    pub async fn ping(&self, addr: SocketAddr, timing: &Timing) -> Result<bool> {
+        let ip = addr.ip();
+        let socket = match ip {
+            IpAddr::V4(_) => &self.socket_v4,
+            IpAddr::V6(_) => self.socket_v6.as_ref()
+                .ok_or_else(|| anyhow::anyhow!("ICMPv6 socket unavailable"))?,
+        };
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed);
+        let packet = build_echo_request(ip, self.identifier, sequence);
+        let mut dest = addr;
+        dest.set_port(0);
+        let dest = SockAddr::from(dest);
+
+        let reply = self.responder
+            .request((ip, sequence), ip, timing, || socket.send_to(&packet, &dest))
+            .await?;
+        Ok(reply.is_some())
+    }
+}
+
+fn echo_reply((ip, reply_identifier, sequence): (IpAddr, u16, u16), identifier: u16) -> Option<((IpAddr, u16), ())> {
+    (reply_identifier == identifier).then_some(((ip, sequence), ()))
+}
+
+fn parse_ipv4_reply(packet: &[u8]) -> Option<(IpAddr, u16, u16)> {
+    if packet.len() < 20 || packet[0] >> 4 != 4 || packet[9] != 1 {
+        return None;
+    }
+    let header_len = ((packet[0] & 0x0f) as usize) * 4;
+    let source = Ipv4Addr::new(packet[12], packet[13], packet[14], packet[15]);
+    parse_echo_reply(IpAddr::V4(source), packet.get(header_len..)?, ICMP_ECHO_REPLY)
+}
+
+fn parse_echo_reply(source: IpAddr, message: &[u8], reply_type: u8) -> Option<(IpAddr, u16, u16)> {
+    if message.len() < 8 || message[0] != reply_type || message[1] != 0 {
+        return None;
+    }
+    let identifier = u16::from_be_bytes([message[4], message[5]]);
+    let sequence = u16::from_be_bytes([message[6], message[7]]);
+    Some((source, identifier, sequence))
+}
+
+fn build_echo_request(dest: IpAddr, identifier: u16, sequence: u16) -> Vec<u8> {
+    let mut message = vec![0u8; 8];
+    message[0] = if dest.is_ipv4() { ICMP_ECHO_REQUEST } else { ICMPV6_ECHO_REQUEST };
+    message[4..6].copy_from_slice(&identifier.to_be_bytes());
+    message[6..8].copy_from_slice(&sequence.to_be_bytes());
+    message.extend_from_slice(ECHO_PAYLOAD);
+
+    if dest.is_ipv4() {
+        let checksum = icmp_checksum(&message);
+        message[2..4].copy_from_slice(&checksum.to_be_bytes());
+    }
+    message
+}
+
+fn icmp_checksum(message: &[u8]) -> u16 {
+    let mut sum: u32 = message
+        .chunks(2)
+        .map(|c| u16::from_be_bytes([c[0], *c.get(1).unwrap_or(&0)]) as u32)
+        .sum();
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
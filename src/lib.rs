@@ -1,5 +1,24 @@
 pub mod scanner;
 pub mod service_detection;
 pub mod patterns;
+pub mod service_probes;
+pub mod nmap_regex;
+pub mod nmap_template;
 pub mod utils;
-pub mod types; 
\ No newline at end of file
+pub mod types;
+pub mod ports;
+pub mod udp;
+pub mod syn;
+pub mod raw_socket;
+pub mod dns;
+pub mod exclude;
+pub mod targets;
+pub mod permutation;
+pub mod rate_limit;
+pub mod timing;
+pub mod congestion;
+pub mod icmp;
+pub mod discovery;
+pub mod arp;
+pub mod mac;
+pub mod output;
@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+use std::fs;
+use anyhow::{Result, Context};
+use crate::types::MacAddress;
+
+const PREFIX_LENGTHS: [usize; 3] = [9, 7, 6];
+
+#[derive(Debug, Clone, Default)]
+pub struct MacVendors {
+    by_prefix: HashMap<String, String>,
+}
+
+impl MacVendors {
+    pub fn load(file_path: &str) -> Result<Self> {
+        let content = fs::read_to_string(file_path)
+            .with_context(|| format!("Failed to read MAC prefix file {}", file_path))?;
+        let mut by_prefix = HashMap::new();
+
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (prefix, vendor) = line
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| anyhow::anyhow!("{}:{}: missing vendor name", file_path, number + 1))?;
+            if !PREFIX_LENGTHS.contains(&prefix.len()) || !prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(anyhow::anyhow!("{}:{}: invalid MAC prefix {}", file_path, number + 1, prefix));
+            }
+            by_prefix.insert(prefix.to_ascii_uppercase(), vendor.trim().to_string());
+        }
+
+        Ok(Self { by_prefix })
+    }
+
+    pub fn lookup(&self, mac: &MacAddress) -> Option<&str> {
+        let hex: String = mac.0.iter().map(|octet| format!("{:02X}", octet)).collect();
+        PREFIX_LENGTHS
+            .iter()
+            .find_map(|&len| self.by_prefix.get(&hex[..len]))
+            .map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_prefix.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_prefix.is_empty()
+    }
+}
@@ -1,174 +1,388 @@
-mod scanner;
-mod service_detection;
-mod utils;
-mod types;
-mod patterns;
-
-use std::net::IpAddr;
-use std::str::FromStr;
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use anyhow::{Result, Context};
-use std::collections::HashMap;
-use crate::scanner::Scanner;
-use crate::types::ScanResult;
-use ipnetwork::IpNetwork;
-use base64::engine::general_purpose::STANDARD;
-use base64::Engine;
+use std::io::{self, BufWriter};
+use std::net::{IpAddr, Ipv6Addr};
+use std::sync::{Arc, Mutex};
+use chrono::Utc;
+use rustcan::scanner::Scanner;
+use rustcan::ports::PortList;
+use rustcan::patterns::{load_nmap_services, NMAP_SERVICES_PATH, NMAP_PROBES_PATH, NMAP_MAC_PREFIXES_PATH};
+use rustcan::service_detection::ServiceProbes;
+use rustcan::udp::UdpPayloads;
+use rustcan::syn::SynScanner;
+use rustcan::types::{ScanType, Target};
+use rustcan::targets::{TargetIter, TargetSpec};
+use rustcan::exclude::ExcludeList;
+use rustcan::output::{reporter, OutputFormat, Reporter, ScanInfo};
+use rustcan::timing::TimingConfig;
+use rustcan::discovery::HostDiscovery;
+use rustcan::icmp::IcmpPinger;
+use rustcan::arp::ArpScanner;
+use rustcan::mac::MacVendors;
+use futures::stream::{self, Stream, StreamExt};
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+
+const OUTPUT_ALL_FORMATS: [(OutputFormat, &str); 5] = [
+    (OutputFormat::Text, "txt"),
+    (OutputFormat::Json, "json"),
+    (OutputFormat::Xml, "xml"),
+    (OutputFormat::Grepable, "gnmap"),
+    (OutputFormat::Csv, "csv"),
+];
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    #[arg(short, long)]
-    target: String,
+    #[arg(short, long, required_unless_present = "input_list", help = "Targets: comma-separated IPs, CIDR networks (with --subnet), ranges like 10.0.1-5.1-254 or 10.0.0.1-10.0.0.50, and hostnames")]
+    target: Option<String>,
+
+    #[arg(short, long, help = "Read targets from a file, one or more per line ('-' for stdin)")]
+    input_list: Option<String>,
+
+    #[arg(long, help = "Comma-separated IPs, CIDR networks and start-end ranges never to scan")]
+    exclude: Option<String>,
+
+    #[arg(long = "excludefile", help = "Read exclusions from a file")]
+    exclude_file: Option<String>,
 
-    #[arg(short, long, default_value = "1000")]
+    #[arg(short, long, alias = "max-parallelism", default_value = "1000", help = "Maximum number of probes in flight")]
     concurrency: usize,
 
-    #[arg(short, long, default_value = "1000")]
-    timeout: u64,
+    #[arg(long, default_value = "10", help = "Minimum number of probes in flight when backing off after packet loss")]
+    min_parallelism: usize,
 
-    #[arg(short, long, default_value = "1-1024")]
+    #[arg(long, help = "Fixed per-probe timeout in milliseconds, disabling adaptive RTT timeouts")]
+    timeout: Option<u64>,
+
+    #[arg(short = 'T', long, default_value = "3", value_parser = clap::value_parser!(u8).range(0..=5), help = "Timing template from 0 (paranoid) to 5 (insane)")]
+    timing: u8,
+
+    #[arg(short, long, default_value = "1-1024", help = "Port specification, e.g. 22,80,443,8000-8100 or T:1-1024,U:53,161")]
     ports: String,
 
+    #[arg(long, conflicts_with = "ports", help = "Scan the N most common ports from nmap-services")]
+    top_ports: Option<usize>,
+
     #[arg(short, long)]
     service_detection: bool,
 
-    #[arg(short, long)]
-    subnet: bool,
-}
+    #[arg(long, help = "Maximum number of probes sent per second across the whole scan")]
+    max_rate: Option<f64>,
 
-fn parse_port_range(ports: &str) -> Result<(u16, u16)> {
-    let parts: Vec<&str> = ports.split('-').collect();
-    if parts.len() != 2 {
-        return Err(anyhow::anyhow!("Invalid port range format. Use start-end"));
-    }
+    #[arg(long, help = "Minimum number of probes per second to sustain; raises parallelism as needed")]
+    min_rate: Option<f64>,
 
-    let start = parts[0].parse::<u16>()
-        .with_context(|| format!("Invalid start port: {}", parts[0]))?;
-    let end = parts[1].parse::<u16>()
-        .with_context(|| format!("Invalid end port: {}", parts[1]))?;
+    #[arg(long, help = "Scan hosts and ports in a randomised order")]
+    randomize: bool,
 
-    if start > end {
-        return Err(anyhow::anyhow!("Start port cannot be greater than end port"));
-    }
+    #[arg(long, help = "Seed for --randomize, to reproduce a previous scan order (implies --randomize)")]
+    seed: Option<u64>,
+
+    #[arg(long, default_value = "80,443,22", help = "TCP ports used as connect pings during host discovery")]
+    discovery_ports: String,
+
+    #[arg(long, help = "Treat every target as up and skip host discovery")]
+    skip_discovery: bool,
+
+    #[arg(long, conflicts_with = "skip_discovery", help = "Only run host discovery and list live hosts")]
+    ping_only: bool,
+
+    #[arg(long, value_enum, default_value = "text", help = "Result format written to stdout or --output")]
+    output_format: OutputFormat,
+
+    #[arg(short, long, help = "Write results to a file instead of stdout")]
+    output: Option<String>,
+
+    #[arg(long, value_name = "FORMAT=PATH", value_parser = parse_output_file, help = "Also write results in another format to a file, e.g. xml=scan.xml (repeatable)")]
+    output_file: Vec<(OutputFormat, String)>,
+
+    #[arg(long, value_name = "BASENAME", help = "Also write text, JSON, XML, grepable and CSV results to BASENAME.txt, .json, .xml, .gnmap and .csv")]
+    output_all: Option<String>,
 
-    Ok((start, end))
+    #[arg(long, help = "Also report closed and filtered ports")]
+    show_closed: bool,
+
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "connect", help = "Scan types to run, e.g. connect,udp or syn,udp")]
+    scan_type: Vec<ScanType>,
+
+    #[arg(long)]
+    subnet: bool,
 }
 
-fn resolve_target(target: &str, subnet: bool) -> Result<Vec<IpAddr>> {
-    if subnet {
-        if let Ok(network) = IpNetwork::from_str(target) {
-            return Ok(network.iter().collect());
-        }
+fn parse_output_file(spec: &str) -> Result<(OutputFormat, String), String> {
+    let (format, path) = spec
+        .split_once('=')
+        .ok_or_else(|| format!("expected FORMAT=PATH, got '{}'", spec))?;
+    if path.is_empty() {
+        return Err(format!("missing path for {} output", format));
     }
+    Ok((OutputFormat::from_str(format, true)?, path.to_string()))
+}
 
-    if let Ok(ip) = IpAddr::from_str(target) {
-        return Ok(vec![ip]);
-    }
+fn create_output(path: &str) -> Result<BufWriter<std::fs::File>> {
+    let file = std::fs::File::create(path).with_context(|| format!("Failed to create output file {}", path))?;
+    Ok(BufWriter::new(file))
+}
 
-    Err(anyhow::anyhow!("Invalid target: {}", target))
+fn parse_target_specs(spec: &str, subnet: bool) -> Result<Vec<TargetSpec>> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| TargetSpec::parse_scannable(item, subnet))
+        .collect()
 }
 
-fn format_scan_result(result: &ScanResult) -> String {
-    let mut output = format!("[+] {}:{} is open", result.ip, result.port);
-    
-    if let Some(service) = &result.service {
-        output.push_str(&format!("\n    Service: {}", service.name));
-        if let Some(version) = &service.version {
-            output.push_str(&format!("\n    Version: {}", version));
-        }
-        if let Some(product) = &service.product {
-            output.push_str(&format!("\n    Product: {}", product));
-        }
-        if let Some(os_type) = &service.os_type {
-            output.push_str(&format!("\n    OS: {}", os_type));
-        }
-        if let Some(extra_info) = &service.extra_info {
-            output.push_str(&format!("\n    Extra Info: {}", extra_info));
-        }
-    }
-    
-    output
+fn expand_targets<S>(specs: S) -> impl Stream<Item = Target>
+where
+    S: Stream<Item = TargetSpec>,
+{
+    specs
+        .then(|spec| async move {
+            match spec.resolve().await {
+                Ok(targets) => stream::iter(targets),
+                Err(e) => {
+                    eprintln!("Warning: skipping target: {}", e);
+                    stream::iter(Box::new(std::iter::empty()) as TargetIter)
+                }
+            }
+        })
+        .flatten()
 }
 
-fn generate_issue_template(result: &ScanResult) -> String {
-    let fingerprint = if !result.raw_response.is_empty() {
-        STANDARD.encode(result.raw_response.as_bytes())
-    } else {
-        "No raw response available".to_string()
-    };
+fn read_target_list<R>(reader: R, subnet: bool, read_error: Arc<Mutex<Option<io::Error>>>) -> impl Stream<Item = TargetSpec>
+where
+    R: AsyncRead + Unpin,
+{
+    let lines = BufReader::new(reader).lines();
+    stream::unfold((lines, read_error), move |(mut lines, read_error)| async move {
+        loop {
+            let line = match lines.next_line().await {
+                Ok(Some(line)) => line,
+                Ok(None) => return None,
+                Err(e) => {
+                    *read_error.lock().unwrap() = Some(e);
+                    return None;
+                }
+            };
 
-    format!(
-        "## Service Fingerprint Report\n\n\
-        ### Target Information\n\
-        - IP: {}\n\
-        - Port: {}\n\
-        - Service: {}\n\n\
-        ### Raw Response (Base64)\n\
-        ```\n\
-        {}\n\
-        ```\n\n\
-        ### Additional Information\n\
-        - Scanner Version: {}\n\
-        - Scan Date: {}\n\n\
-        ### Description\n\
-        Please add a description of the service behavior and any additional context.\n",
-        result.ip,
-        result.port,
-        result.service.as_ref().map(|s| s.name.clone()).unwrap_or_else(|| "unknown".to_string()),
-        fingerprint,
-        env!("CARGO_PKG_VERSION"),
-        chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
-    )
+            let line = line.split('#').next().unwrap_or("");
+            let mut specs = Vec::new();
+            for item in line.split(|c: char| c.is_whitespace() || c == ',').filter(|i| !i.is_empty()) {
+                match TargetSpec::parse_scannable(item, subnet) {
+                    Ok(spec) => specs.push(spec),
+                    Err(e) => eprintln!("Warning: skipping target {}: {}", item, e),
+                }
+            }
+
+            if !specs.is_empty() {
+                return Some((stream::iter(specs), (lines, read_error)));
+            }
+        }
+    })
+    .flatten()
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
-    let (start_port, end_port) = parse_port_range(&args.ports)?;
-    let targets = resolve_target(&args.target, args.subnet)?;
+    let mut reporters: Vec<Box<dyn Reporter>> = vec![match &args.output {
+        Some(path) => reporter(args.output_format, create_output(path)?),
+        None => reporter(args.output_format, io::stdout()),
+    }];
+    for (format, path) in &args.output_file {
+        reporters.push(reporter(*format, create_output(path)?));
+    }
+    if let Some(basename) = &args.output_all {
+        for (format, extension) in OUTPUT_ALL_FORMATS {
+            reporters.push(reporter(format, create_output(&format!("{}.{}", basename, extension))?));
+        }
+    }
+    let services = load_nmap_services(NMAP_SERVICES_PATH)?;
+    let ports = match args.top_ports {
+        Some(count) => PortList::top(&services, count)?,
+        None => PortList::parse(&args.ports, &services)?,
+    };
+    let mut exclusions = ExcludeList::default();
+    if let Some(exclude) = &args.exclude {
+        exclusions.extend(ExcludeList::parse(exclude)?);
+    }
+    if let Some(exclude_file) = &args.exclude_file {
+        exclusions.extend(ExcludeList::load(exclude_file)?);
+    }
 
-    println!("Starting scan on {} targets...", targets.len());
+    let target_specs = match &args.target {
+        Some(target) => parse_target_specs(target, args.subnet)?,
+        None => Vec::new(),
+    };
+
+    let known_count: u128 = target_specs.iter().filter_map(TargetSpec::address_count).sum();
+    let hostnames = target_specs.iter().filter(|spec| spec.address_count().is_none()).count();
+    if args.input_list.is_none() {
+        if hostnames > 0 {
+            eprintln!("Starting scan on {} addresses and {} host names...", known_count, hostnames);
+        } else {
+            eprintln!("Starting scan on {} targets...", known_count);
+        }
+    }
 
-    let scanner = Scanner::new(
-        targets,
-        start_port..=end_port,
+    let scan_ports = ports.clone();
+    let mut scanner = Scanner::new(
+        Vec::new(),
+        ports,
         args.concurrency,
-        args.timeout,
         args.service_detection,
     );
 
-    let results = scanner.run().await;
-    let mut service_stats: HashMap<String, u32> = HashMap::new();
-
-    for result in &results {
-        if let Some(service) = &result.service {
-            *service_stats.entry(service.name.clone()).or_insert(0) += 1;
+    let mut scan_types = Vec::new();
+    for scan_type in args.scan_type {
+        if !scan_types.contains(&scan_type) {
+            scan_types.push(scan_type);
         }
     }
+    if scan_types.contains(&ScanType::Connect) && scan_types.contains(&ScanType::Syn) {
+        return Err(anyhow::anyhow!("Only one TCP scan type (connect or syn) can be used at a time"));
+    }
 
-    println!("\nScan Results:");
-    for result in results {
-        if let Some(service) = result.service {
-            println!("[+] {}:{} is open", result.ip, result.port);
-            println!("    Service: {}", service.name);
-            if let Some(version) = service.version {
-                println!("    Version: {}", version);
+    if scan_types.contains(&ScanType::Udp) {
+        let payloads = UdpPayloads::load(NMAP_PROBES_PATH)?;
+        eprintln!("Loaded UDP payloads for {} ports", payloads.len());
+        scanner = scanner.with_udp_payloads(payloads);
+    }
+    if args.service_detection {
+        let probes = ServiceProbes::load(NMAP_PROBES_PATH)?;
+        eprintln!("Loaded {} service patterns ({} unsupported)", probes.loaded(), probes.unsupported());
+        scanner = scanner.with_service_probes(probes);
+    }
+    if scan_types.contains(&ScanType::Syn) {
+        match SynScanner::new() {
+            Ok(syn_scanner) => {
+                if !syn_scanner.supports(IpAddr::V6(Ipv6Addr::UNSPECIFIED)) {
+                    eprintln!("Warning: IPv6 raw socket unavailable; IPv6 targets fall back to TCP connect scan");
+                }
+                scanner = scanner.with_syn_scanner(syn_scanner);
+            }
+            Err(e) => {
+                eprintln!("Warning: {}; falling back to TCP connect scan", e);
+                for scan_type in &mut scan_types {
+                    if *scan_type == ScanType::Syn {
+                        *scan_type = ScanType::Connect;
+                    }
+                }
+            }
+        }
+    }
+    if args.concurrency == 0 || args.min_parallelism == 0 {
+        return Err(anyhow::anyhow!("Parallelism must be at least 1"));
+    }
+    if args.min_parallelism > args.concurrency {
+        return Err(anyhow::anyhow!("--min-parallelism cannot be greater than --concurrency"));
+    }
+    let timing = TimingConfig::template(args.timing)?;
+    let timing = match args.timeout {
+        Some(0) => return Err(anyhow::anyhow!("--timeout must be greater than zero")),
+        Some(timeout) => timing.with_fixed_timeout(std::time::Duration::from_millis(timeout)),
+        None => timing,
+    };
+    let scanner = scanner
+        .with_timing(timing)
+        .with_min_parallelism(args.min_parallelism)
+        .with_scan_types(scan_types.clone())
+        .with_report_closed(args.show_closed)
+        .with_exclusions(exclusions);
+    for rate in [args.max_rate, args.min_rate].into_iter().flatten() {
+        if !rate.is_finite() || rate <= 0.0 {
+            return Err(anyhow::anyhow!("Probe rates must be positive numbers"));
+        }
+    }
+    if let (Some(min_rate), Some(max_rate)) = (args.min_rate, args.max_rate) {
+        if min_rate > max_rate {
+            return Err(anyhow::anyhow!("--min-rate cannot be greater than --max-rate"));
+        }
+    }
+    let scanner = if args.skip_discovery {
+        scanner
+    } else {
+        let discovery_ports = match args.discovery_ports.trim() {
+            "" => Vec::new(),
+            spec => PortList::parse(spec, &services)?.tcp,
+        };
+        let discovery = HostDiscovery::new(discovery_ports.clone());
+        let discovery = match IcmpPinger::new() {
+            Ok(icmp) => discovery.with_icmp(icmp),
+            Err(_) if discovery_ports.is_empty() => {
+                return Err(anyhow::anyhow!("Host discovery needs --discovery-ports when ICMP echo is unavailable"));
             }
-            if let Some(product) = service.product {
-                println!("    Product: {}", product);
+            Err(e) => {
+                eprintln!("Warning: {}; host discovery will use TCP pings only", e);
+                discovery
             }
-            if let Some(os) = service.os_type {
-                println!("    OS: {}", os);
+        };
+        let discovery = match ArpScanner::new() {
+            Ok(arp) => discovery.with_arp(arp),
+            Err(_) => discovery,
+        };
+        let discovery = match MacVendors::load(NMAP_MAC_PREFIXES_PATH) {
+            Ok(mac_vendors) => discovery.with_mac_vendors(mac_vendors),
+            Err(e) => {
+                eprintln!("Warning: {}; MAC vendors will not be reported", e);
+                discovery
             }
-        } else {
-            println!("[+] {}:{} is open", result.ip, result.port);
+        };
+        scanner.with_discovery(discovery)
+    };
+    let scanner = match args.max_rate {
+        Some(max_rate) => scanner.with_max_rate(max_rate),
+        None => scanner,
+    };
+    let scanner = match args.min_rate {
+        Some(min_rate) => scanner.with_min_rate(min_rate),
+        None => scanner,
+    };
+    let scanner = if args.randomize || args.seed.is_some() {
+        let seed = args.seed.unwrap_or_else(rand::random);
+        eprintln!("Randomizing scan order with seed {}", seed);
+        scanner.with_randomize_seed(seed)
+    } else {
+        scanner
+    };
+    let scanner = if args.input_list.is_none() && hostnames == 0 {
+        scanner.with_target_count(u64::try_from(known_count).unwrap_or(u64::MAX))
+    } else {
+        scanner
+    };
+
+    let read_error = Arc::new(Mutex::new(None));
+    let specs = stream::iter(target_specs);
+    let specs = match &args.input_list {
+        Some(path) => {
+            let reader: Box<dyn AsyncRead + Unpin + Send> = if path == "-" {
+                Box::new(tokio::io::stdin())
+            } else {
+                Box::new(File::open(path).await.with_context(|| format!("Failed to open input list {}", path))?)
+            };
+            eprintln!("Starting scan on targets from {}...", if path == "-" { "stdin" } else { path });
+            specs.chain(read_target_list(reader, args.subnet, read_error.clone())).left_stream()
         }
+        None => specs.right_stream(),
+    };
+
+    let info = ScanInfo {
+        command_line: std::env::args().collect(),
+        scan_types: scan_types.clone(),
+        ports: if args.ping_only { PortList::default() } else { scan_ports },
+        ping_only: args.ping_only,
+        start_time: Utc::now(),
+    };
+
+    if args.ping_only {
+        scanner.report_ping_sweep(expand_targets(specs), &info, &mut reporters).await?;
+    } else {
+        scanner.report_stream(expand_targets(specs), &info, &mut reporters).await?;
     }
 
-    println!("\nService Statistics:");
-    for (service, count) in service_stats {
-        println!("  {}: {}", service, count);
+    if let Some(e) = read_error.lock().unwrap().take() {
+        return Err(e).context("Failed to read input list; the scan is incomplete");
     }
 
     Ok(())
@@ -0,0 +1,286 @@
+use anyhow::Result;
+use regex::bytes::{Regex, RegexBuilder};
+use crate::types::NmapMatch;
+
+const REGEX_SIZE_LIMIT: usize = 16 * 1024 * 1024;
+
+pub fn compile(nmap_match: &NmapMatch) -> Result<Regex> {
+    let pattern = translate(&nmap_match.pattern)?;
+    Ok(RegexBuilder::new(&pattern)
+        .unicode(false)
+        .case_insensitive(nmap_match.case_insensitive)
+        .dot_matches_new_line(nmap_match.dot_all)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .build()?)
+}
+
+pub fn translate(pattern: &str) -> Result<String> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut translated = String::with_capacity(pattern.len() + 16);
+    let mut in_class = false;
+    let mut class_start = 0;
+    let mut after_quantifier = false;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let quantifier = after_quantifier;
+        after_quantifier = false;
+
+        if c == '\\' {
+            i = translate_escape(&chars, i + 1, in_class, &mut translated)?;
+            continue;
+        }
+
+        if in_class {
+            match c {
+                ']' if i > class_start => in_class = false,
+                '[' if chars.get(i + 1) == Some(&':') => {
+                    let end = chars[i..]
+                        .windows(2)
+                        .position(|pair| pair == [':', ']'])
+                        .ok_or_else(|| anyhow::anyhow!("unterminated POSIX class"))?;
+                    translated.extend(&chars[i..i + end + 2]);
+                    i += end + 2;
+                    continue;
+                }
+                '[' | '&' | '~' | '-' if c != '-' || chars.get(i + 1) == Some(&'-') => {
+                    push_byte(&mut translated, c as u8);
+                    i += 1;
+                    continue;
+                }
+                _ => {}
+            }
+            translated.push(c);
+            i += 1;
+            continue;
+        }
+
+        match c {
+            '[' => {
+                in_class = true;
+                translated.push('[');
+                i += 1;
+                if chars.get(i) == Some(&'^') {
+                    translated.push('^');
+                    i += 1;
+                }
+                class_start = i;
+                if chars.get(i) == Some(&']') {
+                    translated.push_str("\\]");
+                    i += 1;
+                }
+                continue;
+            }
+            '(' if chars.get(i + 1) == Some(&'?') => {
+                let rest: String = chars[i + 2..].iter().take(2).collect();
+                if rest.starts_with('=') || rest.starts_with('!') || rest == "<=" || rest == "<!" {
+                    return Err(anyhow::anyhow!("lookaround assertions are not supported"));
+                }
+                if rest.starts_with('>') {
+                    translated.push_str("(?:");
+                    i += 3;
+                    continue;
+                }
+                translated.push(c);
+            }
+            '*' | '?' => {
+                translated.push(c);
+                after_quantifier = !quantifier;
+            }
+            '+' => {
+                if !quantifier {
+                    translated.push(c);
+                    after_quantifier = true;
+                }
+            }
+            '{' => match repetition(&chars, i) {
+                Some(end) => {
+                    translated.extend(&chars[i..=end]);
+                    i = end + 1;
+                    after_quantifier = true;
+                    continue;
+                }
+                None => translated.push_str("\\{"),
+            },
+            '}' => translated.push_str("\\}"),
+            '$' => translated.push_str("(?:\\n?\\z)"),
+            _ => translated.push(c),
+        }
+        i += 1;
+    }
+
+    if in_class {
+        return Err(anyhow::anyhow!("unterminated character class"));
+    }
+    Ok(translated)
+}
+
+fn translate_escape(chars: &[char], start: usize, in_class: bool, translated: &mut String) -> Result<usize> {
+    let c = *chars
+        .get(start)
+        .ok_or_else(|| anyhow::anyhow!("pattern ends with a backslash"))?;
+    let mut next = start + 1;
+
+    match c {
+        '0' => {
+            let digits: String = chars[next..]
+                .iter()
+                .take(2)
+                .take_while(|c| c.is_digit(8))
+                .collect();
+            next += digits.len();
+            let value = u8::from_str_radix(&digits, 8).unwrap_or(0);
+            push_byte(translated, value);
+        }
+        'x' if chars.get(next) == Some(&'{') => {
+            let end = next + chars[next..]
+                .iter()
+                .position(|&c| c == '}')
+                .ok_or_else(|| anyhow::anyhow!("unterminated \\x{{...}} escape"))?;
+            let digits: String = chars[next + 1..end].iter().collect();
+            let value = u8::from_str_radix(&digits, 16)
+                .map_err(|_| anyhow::anyhow!("invalid byte escape '\\x{{{}}}'", digits))?;
+            push_byte(translated, value);
+            next = end + 1;
+        }
+        'x' => {
+            let digits: String = chars[next..]
+                .iter()
+                .take(2)
+                .take_while(|c| c.is_ascii_hexdigit())
+                .collect();
+            next += digits.len();
+            let value = u8::from_str_radix(&digits, 16).unwrap_or(0);
+            push_byte(translated, value);
+        }
+        'a' => push_byte(translated, 0x07),
+        'e' => push_byte(translated, 0x1B),
+        'b' if in_class => push_byte(translated, 0x08),
+        'h' if in_class => translated.push_str("\\t "),
+        'h' => translated.push_str("[\\t ]"),
+        'Z' if !in_class => translated.push_str("(?:\\n?\\z)"),
+        '1'..='9' => return Err(anyhow::anyhow!("backreferences are not supported")),
+        'd' | 'D' | 'w' | 'W' | 's' | 'S' | 'n' | 'r' | 't' | 'f' | 'v' => {
+            translated.push('\\');
+            translated.push(c);
+        }
+        'b' | 'B' | 'A' | 'z' if !in_class => {
+            translated.push('\\');
+            translated.push(c);
+        }
+        c if c.is_ascii_alphanumeric() => {
+            return Err(anyhow::anyhow!("unsupported escape '\\{}'", c));
+        }
+        c if c.is_ascii() => push_byte(translated, c as u8),
+        c => translated.push_str(&regex::escape(&c.to_string())),
+    }
+    Ok(next)
+}
+
+fn repetition(chars: &[char], start: usize) -> Option<usize> {
+    let end = start + chars[start..].iter().position(|&c| c == '}')?;
+    let body: String = chars[start + 1..end].iter().collect();
+    let (min, max) = body.split_once(',').unwrap_or((&body, &body));
+    let valid = !min.is_empty()
+        && min.chars().all(|c| c.is_ascii_digit())
+        && max.chars().all(|c| c.is_ascii_digit());
+    valid.then_some(end)
+}
+
+fn push_byte(translated: &mut String, byte: u8) {
+    translated.push_str(&format!("\\x{:02X}", byte));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nmap_match(pattern: &str) -> NmapMatch {
+        NmapMatch {
+            service: "test".to_string(),
+            pattern: pattern.to_string(),
+            soft: false,
+            case_insensitive: false,
+            dot_all: false,
+            version_info: None,
+            product_info: None,
+            os_info: None,
+            extra_info: None,
+            hostname_info: None,
+            device_type: None,
+            cpes: Vec::new(),
+        }
+    }
+
+    fn is_match(pattern: &str, input: &[u8]) -> bool {
+        compile(&nmap_match(pattern)).unwrap().is_match(input)
+    }
+
+    #[test]
+    fn dollar_and_z_match_before_a_final_newline() {
+        assert_eq!(translate("ok$").unwrap(), "ok(?:\\n?\\z)");
+        assert_eq!(translate(r"ok\Z").unwrap(), "ok(?:\\n?\\z)");
+        for pattern in ["^ok$", r"^ok\Z"] {
+            assert!(is_match(pattern, b"ok"));
+            assert!(is_match(pattern, b"ok\n"));
+            assert!(!is_match(pattern, b"ok\nmore"));
+            assert!(!is_match(pattern, b"ok\n\n"));
+        }
+        assert!(is_match(r"^ok\z", b"ok"));
+        assert!(!is_match(r"^ok\z", b"ok\n"));
+    }
+
+    #[test]
+    fn possessive_quantifiers_become_greedy() {
+        assert_eq!(translate("a++b*+c?+d{2}+").unwrap(), "a+b*c?d{2}");
+        assert_eq!(translate(r"a\++").unwrap(), "a\\x2B+");
+        assert!(is_match(r"^\d++-\w*+$", b"123-abc"));
+    }
+
+    #[test]
+    fn atomic_groups_become_non_capturing() {
+        assert_eq!(translate("(?>ab|a)c").unwrap(), "(?:ab|a)c");
+        assert!(is_match("^(?>[a-z]+)(\\d)$", b"abc1"));
+        let captures = compile(&nmap_match("(?>x)(y)")).unwrap();
+        assert_eq!(&captures.captures(b"xy").unwrap()[1], b"y");
+    }
+
+    #[test]
+    fn rejects_lookarounds_and_backreferences() {
+        for pattern in ["a(?=b)", "a(?!b)", "(?<=a)b", "(?<!a)b", r"(a)\1", r"(a)(b)\2"] {
+            assert!(translate(pattern).is_err(), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn translates_byte_escapes() {
+        assert!(is_match(r"^\x00\xff\x{7f}\0\012\a\e$", b"\x00\xff\x7f\x00\x0a\x07\x1b"));
+        assert!(is_match(r"^[\xd5-\xd7]$", b"\xd6"));
+        assert!(!is_match(r"^[\xd5-\xd7]$", b"\xd8"));
+        assert!(is_match(r"^[\b]\h[\h]$", b"\x08\t "));
+        assert!(is_match(r"^a\.b\-\/$", b"a.b-/"));
+        assert!(translate(r"\q").is_err());
+        assert!(translate(r"\x{zz}").is_err());
+        assert!(translate("trailing\\").is_err());
+    }
+
+    #[test]
+    fn escapes_literal_braces_and_class_operators() {
+        assert!(is_match(r"^{a}x{,2}$", b"{a}x{,2}"));
+        assert!(is_match("^a{2,3}$", b"aaa"));
+        assert!(is_match("^[]a]+$", b"]a]"));
+        assert!(is_match("^[a&&b~~c--d[]+$", b"&~-["));
+        assert!(is_match("^[[:digit:]x]+$", b"1x2"));
+        assert!(translate("[abc").is_err());
+    }
+
+    #[test]
+    fn honours_match_flags() {
+        let mut info = nmap_match("^hello.world$");
+        assert!(!compile(&info).unwrap().is_match(b"HELLO\nworld"));
+        info.case_insensitive = true;
+        info.dot_all = true;
+        assert!(compile(&info).unwrap().is_match(b"HELLO\nworld"));
+    }
+}
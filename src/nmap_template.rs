@@ -0,0 +1,196 @@
+use anyhow::Result;
+use regex::bytes::Captures;
+
+const MAX_INTEGER_BYTES: usize = 8;
+
+enum Argument {
+    Group(usize),
+    Text(String),
+}
+
+pub fn render(template: &str, captures: &Captures) -> Result<Option<String>> {
+    let mut rendered = Vec::with_capacity(template.len());
+    let mut i = 0;
+
+    while i < template.len() {
+        let rest = &template[i..];
+        if !rest.starts_with('$') {
+            let c = rest.chars().next().unwrap_or_default();
+            rendered.extend_from_slice(&rest.as_bytes()[..c.len_utf8()]);
+            i += c.len_utf8();
+            continue;
+        }
+
+        if let Some(digit) = rest[1..].chars().next().and_then(|c| c.to_digit(10)) {
+            rendered.extend_from_slice(group(captures, digit as usize));
+            i += 2;
+            continue;
+        }
+
+        let name_len = rest[1..].find(|c: char| !c.is_ascii_uppercase()).unwrap_or(rest.len() - 1);
+        let name = &rest[1..1 + name_len];
+        if name.is_empty() || !rest[1 + name_len..].starts_with('(') {
+            rendered.push(b'$');
+            i += 1;
+            continue;
+        }
+
+        let arguments_start = 2 + name_len;
+        let arguments_len = closing_paren(&rest[arguments_start..])
+            .ok_or_else(|| anyhow::anyhow!("unterminated ${}( in version template", name))?;
+        let arguments = parse_arguments(&rest[arguments_start..arguments_start + arguments_len])?;
+        rendered.extend(call(name, &arguments, captures)?);
+        i += arguments_start + arguments_len + 1;
+    }
+
+    let rendered = String::from_utf8_lossy(&rendered).trim().to_string();
+    Ok(Some(rendered).filter(|value| !value.is_empty()))
+}
+
+fn call(name: &str, arguments: &[Argument], captures: &Captures) -> Result<Vec<u8>> {
+    match (name, arguments) {
+        ("P", [Argument::Group(index)]) => Ok(group(captures, *index)
+            .iter()
+            .copied()
+            .filter(|byte| byte.is_ascii_graphic() || *byte == b' ')
+            .collect()),
+        ("SUBST", [Argument::Group(index), Argument::Text(from), Argument::Text(to)]) => {
+            Ok(substitute(group(captures, *index), from.as_bytes(), to.as_bytes()))
+        }
+        ("I", [Argument::Group(index), Argument::Text(endianness)]) => {
+            let bytes = group(captures, *index);
+            if bytes.len() > MAX_INTEGER_BYTES {
+                return Err(anyhow::anyhow!("$I() group is longer than {} bytes", MAX_INTEGER_BYTES));
+            }
+            let fold = |value: u64, byte: &u8| (value << 8) | u64::from(*byte);
+            let value = match endianness.as_str() {
+                ">" => bytes.iter().fold(0, fold),
+                "<" => bytes.iter().rev().fold(0, fold),
+                _ => return Err(anyhow::anyhow!("$I() endianness must be \">\" or \"<\"")),
+            };
+            Ok(value.to_string().into_bytes())
+        }
+        _ => Err(anyhow::anyhow!("unsupported version template helper ${}()", name)),
+    }
+}
+
+fn group<'a>(captures: &Captures<'a>, index: usize) -> &'a [u8] {
+    captures.get(index).map(|m| m.as_bytes()).unwrap_or_default()
+}
+
+fn substitute(value: &[u8], from: &[u8], to: &[u8]) -> Vec<u8> {
+    if from.is_empty() {
+        return value.to_vec();
+    }
+
+    let mut substituted = Vec::with_capacity(value.len());
+    let mut i = 0;
+    while i < value.len() {
+        if value[i..].starts_with(from) {
+            substituted.extend_from_slice(to);
+            i += from.len();
+        } else {
+            substituted.push(value[i]);
+            i += 1;
+        }
+    }
+    substituted
+}
+
+fn closing_paren(arguments: &str) -> Option<usize> {
+    let mut quoted = false;
+    arguments.char_indices().find_map(|(index, c)| {
+        match c {
+            '"' => quoted = !quoted,
+            ')' if !quoted => return Some(index),
+            _ => {}
+        }
+        None
+    })
+}
+
+fn parse_arguments(arguments: &str) -> Result<Vec<Argument>> {
+    let mut parsed = Vec::new();
+    let mut rest = arguments.trim_start();
+
+    while !rest.is_empty() {
+        if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted
+                .find('"')
+                .ok_or_else(|| anyhow::anyhow!("unterminated string in version template"))?;
+            parsed.push(Argument::Text(quoted[..end].to_string()));
+            rest = &quoted[end + 1..];
+        } else {
+            let end = rest.find(',').unwrap_or(rest.len());
+            let index = rest[..end].trim();
+            let index = index
+                .parse()
+                .map_err(|_| anyhow::anyhow!("invalid group '{}' in version template", index))?;
+            parsed.push(Argument::Group(index));
+            rest = &rest[end..];
+        }
+
+        rest = rest.trim_start();
+        if let Some(next) = rest.strip_prefix(',') {
+            rest = next.trim_start();
+        } else if !rest.is_empty() {
+            return Err(anyhow::anyhow!("expected ',' between version template arguments"));
+        }
+    }
+
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::bytes::Regex;
+
+    fn render_with(pattern: &str, input: &[u8], template: &str) -> Result<Option<String>> {
+        let regex = Regex::new(pattern).unwrap();
+        let captures = regex.captures(input).unwrap();
+        render(template, &captures)
+    }
+
+    #[test]
+    fn substitutes_groups() {
+        let rendered = render_with(r"(?-u)^(\w+) (\d+)\.(\d+)", b"OpenSSH 9.6", "$1 version $2.$3").unwrap();
+        assert_eq!(rendered.as_deref(), Some("OpenSSH version 9.6"));
+        assert_eq!(render_with("(a)(b)?", b"a", "x$2y$9").unwrap().as_deref(), Some("xy"));
+        assert_eq!(render_with("(a)", b"a", "  $2  ").unwrap(), None);
+        assert_eq!(render_with("(a)", b"a", "cost $ and $$1").unwrap().as_deref(), Some("cost $ and $a"));
+    }
+
+    #[test]
+    fn printable_helper_drops_non_printable_bytes() {
+        let rendered = render_with(r"(?s-u)^(.*)$", b"ab\x00c\r\n d\xff", "$P(1)").unwrap();
+        assert_eq!(rendered.as_deref(), Some("abc d"));
+    }
+
+    #[test]
+    fn subst_helper_replaces_every_occurrence() {
+        let rendered = render_with(r"^(\S+)", b"1_2_3", r#"v$SUBST(1,"_",".")"#).unwrap();
+        assert_eq!(rendered.as_deref(), Some("v1.2.3"));
+        let rendered = render_with(r"^(\S+)", b"a,b", r#"$SUBST( 1 , "," , ", " )!"#).unwrap();
+        assert_eq!(rendered.as_deref(), Some("a, b!"));
+        let rendered = render_with(r"^(\S+)", b"abc", r#"$SUBST(1,"","x")"#).unwrap();
+        assert_eq!(rendered.as_deref(), Some("abc"));
+    }
+
+    #[test]
+    fn integer_helper_reads_both_byte_orders() {
+        let input = b"\x01\x02";
+        assert_eq!(render_with(r"(?s-u)^(..)", input, r#"$I(1,">")"#).unwrap().as_deref(), Some("258"));
+        assert_eq!(render_with(r"(?s-u)^(..)", input, r#"$I(1,"<")"#).unwrap().as_deref(), Some("513"));
+        assert!(render_with(r"(?s-u)^(.{9})", b"123456789", r#"$I(1,">")"#).is_err());
+        assert!(render_with(r"(?s-u)^(..)", input, r#"$I(1,"=")"#).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_helpers() {
+        for template in [r#"$P(1"#, r#"$SUBST(1,"a)"#, "$P(x)", r#"$P(1,"a")"#, "$NOPE(1)", r#"$SUBST(1 "a","b")"#] {
+            assert!(render_with("(a)", b"a", template).is_err(), "{}", template);
+        }
+        assert_eq!(render_with("(a)", b"a", "$P and $p(1)").unwrap().as_deref(), Some("$P and $p(1)"));
+    }
+}
@@ -0,0 +1,67 @@
+use std::io::Write;
+use anyhow::Result;
+use crate::types::Service;
+use super::{HostReport, ReportBuffer, ScanSummary};
+
+pub(super) fn render<W: Write>(writer: &mut W, buffer: &ReportBuffer, _summary: &ScanSummary) -> Result<()> {
+    write_csv(writer, &buffer.hosts())
+}
+
+fn write_csv<W: Write>(writer: &mut W, hosts: &[HostReport]) -> Result<()> {
+    writeln!(
+        writer,
+        "ip,zone,hostname,mac,vendor,port,protocol,state,service,product,version,extra_info,os_type,cpe,banner"
+    )?;
+
+    for host in hosts {
+        let host_fields = [
+            host.ip.to_string(),
+            host.zone.as_ref().map(ToString::to_string).unwrap_or_default(),
+            host.hostname.clone().unwrap_or_default(),
+            host.mac.map(|mac| mac.to_string()).unwrap_or_default(),
+            host.vendor.clone().unwrap_or_default(),
+        ];
+        if host.ports.is_empty() {
+            write_csv_row(writer, host_fields.iter().map(String::as_str).chain([""; 10]))?;
+            continue;
+        }
+        for port in &host.ports {
+            let service = port.service.as_ref();
+            let service_field = |field: fn(&Service) -> &Option<String>| {
+                service.and_then(|service| field(service).as_deref()).unwrap_or("")
+            };
+            let port_number = port.port.to_string();
+            let protocol = port.protocol.to_string();
+            let state = port.state.to_string();
+            let port_fields = [
+                port_number.as_str(),
+                protocol.as_str(),
+                state.as_str(),
+                service.map(|service| service.name.as_str()).unwrap_or(""),
+                service_field(|service| &service.product),
+                service_field(|service| &service.version),
+                service_field(|service| &service.extra_info),
+                service_field(|service| &service.os_type),
+                service_field(|service| &service.cpe),
+                port.banner.as_deref().unwrap_or(""),
+            ];
+            write_csv_row(writer, host_fields.iter().map(String::as_str).chain(port_fields))?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_csv_row<'a, W: Write>(writer: &mut W, fields: impl Iterator<Item = &'a str>) -> Result<()> {
+    let row: Vec<String> = fields
+        .map(|field| {
+            if field.contains([',', '"', '\r', '\n']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        })
+        .collect();
+    writeln!(writer, "{}", row.join(","))?;
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use std::io::Write;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use crate::scanner::HostCounts;
+use crate::utils::format_ip;
+use super::{ctime, HostReport, ReportBuffer, ScanInfo, ScanSummary};
+
+pub(super) fn render<W: Write>(writer: &mut W, buffer: &ReportBuffer, summary: &ScanSummary) -> Result<()> {
+    let hosts = buffer.hosts();
+    write_grepable(writer, buffer.info()?, summary.end_time, &hosts, summary.hosts)
+}
+
+fn write_grepable<W: Write>(
+    writer: &mut W,
+    info: &ScanInfo,
+    end_time: DateTime<Utc>,
+    hosts: &[HostReport],
+    counts: HostCounts,
+) -> Result<()> {
+    writeln!(
+        writer,
+        "# {} {} scan initiated {} as: {}",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        ctime(info.start_time),
+        info.command_line(),
+    )?;
+
+    for host in hosts {
+        let host_field = format!(
+            "Host: {} ({})",
+            format_ip(host.ip, host.zone.as_ref()),
+            host.hostname.as_deref().unwrap_or(""),
+        );
+        writeln!(writer, "{}\tStatus: Up", host_field)?;
+        if host.ports.is_empty() {
+            continue;
+        }
+        let ports: Vec<String> = host.ports
+            .iter()
+            .map(|port| {
+                let service = port.service.as_ref();
+                let version = service
+                    .map(|service| {
+                        [&service.product, &service.version, &service.extra_info]
+                            .into_iter()
+                            .flatten()
+                            .map(String::as_str)
+                            .collect::<Vec<_>>()
+                            .join(" ")
+                    })
+                    .unwrap_or_default();
+                format!(
+                    "{}/{}/{}//{}//{}/",
+                    port.port,
+                    port.state,
+                    port.protocol,
+                    grepable_field(service.map(|service| service.name.as_str()).unwrap_or("")),
+                    grepable_field(&version),
+                )
+            })
+            .collect();
+        writeln!(writer, "{}\tPorts: {}", host_field, ports.join(", "))?;
+    }
+
+    let total = counts.up + counts.down;
+    writeln!(
+        writer,
+        "# {} done at {} -- {} IP address{} ({} host{} up) scanned in {:.2} seconds",
+        env!("CARGO_PKG_NAME"),
+        ctime(end_time),
+        total,
+        if total == 1 { "" } else { "es" },
+        counts.up,
+        if counts.up == 1 { "" } else { "s" },
+        info.elapsed_seconds(end_time),
+    )?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn grepable_field(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '/' => '|',
+            ',' => ';',
+            c if c.is_control() => ' ',
+            c => c,
+        })
+        .collect()
+}
@@ -0,0 +1,49 @@
+use std::io::Write;
+use anyhow::Result;
+use serde::Serialize;
+use crate::types::{PortState, ScanType};
+use super::{timestamp, HostReport, JsonStats, ReportBuffer, ScanSummary, JSON_FORMAT_VERSION};
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    scanner: &'static str,
+    version: &'static str,
+    format_version: u32,
+    command_line: String,
+    start_time: String,
+    end_time: String,
+    elapsed_seconds: f64,
+    scan_types: &'a [ScanType],
+    stats: JsonStats,
+    hosts: &'a [HostReport],
+}
+
+pub(super) fn render<W: Write>(writer: &mut W, buffer: &ReportBuffer, summary: &ScanSummary) -> Result<()> {
+    let info = buffer.info()?;
+    let hosts = buffer.hosts();
+    let report = JsonReport {
+        scanner: env!("CARGO_PKG_NAME"),
+        version: env!("CARGO_PKG_VERSION"),
+        format_version: JSON_FORMAT_VERSION,
+        command_line: info.command_line(),
+        start_time: timestamp(info.start_time),
+        end_time: timestamp(summary.end_time),
+        elapsed_seconds: info.elapsed_seconds(summary.end_time),
+        scan_types: &info.scan_types,
+        stats: JsonStats {
+            hosts: hosts.len(),
+            ports: hosts.iter().map(|host| host.ports.len()).sum(),
+            open_ports: hosts
+                .iter()
+                .flat_map(|host| &host.ports)
+                .filter(|port| port.state == PortState::Open)
+                .count(),
+        },
+        hosts: &hosts,
+    };
+
+    serde_json::to_writer_pretty(&mut *writer, &report)?;
+    writeln!(writer)?;
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,266 @@
+mod csv;
+mod grepable;
+mod json;
+mod ndjson;
+mod text;
+mod xml;
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::net::IpAddr;
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use crate::ports::PortList;
+use crate::scanner::HostCounts;
+use crate::types::{MacAddress, PortState, Protocol, ScanResult, ScanType, Service, Target, Zone};
+
+pub use self::ndjson::NdjsonReporter;
+
+pub const JSON_FORMAT_VERSION: u32 = 1;
+pub const NMAP_XML_OUTPUT_VERSION: &str = "1.05";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Ndjson,
+    Xml,
+    Grepable,
+    Csv,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScanInfo {
+    pub command_line: Vec<String>,
+    pub scan_types: Vec<ScanType>,
+    pub ports: PortList,
+    pub ping_only: bool,
+    pub start_time: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ScanSummary {
+    pub end_time: DateTime<Utc>,
+    pub hosts: HostCounts,
+}
+
+pub trait Reporter: Send {
+    fn scan_start(&mut self, info: &ScanInfo) -> Result<()>;
+    fn host(&mut self, target: &Target) -> Result<()>;
+    fn port_result(&mut self, result: &ScanResult) -> Result<()>;
+    fn scan_end(&mut self, summary: &ScanSummary) -> Result<()>;
+}
+
+impl Reporter for Vec<Box<dyn Reporter>> {
+    fn scan_start(&mut self, info: &ScanInfo) -> Result<()> {
+        self.iter_mut().try_for_each(|reporter| reporter.scan_start(info))
+    }
+
+    fn host(&mut self, target: &Target) -> Result<()> {
+        self.iter_mut().try_for_each(|reporter| reporter.host(target))
+    }
+
+    fn port_result(&mut self, result: &ScanResult) -> Result<()> {
+        self.iter_mut().try_for_each(|reporter| reporter.port_result(result))
+    }
+
+    fn scan_end(&mut self, summary: &ScanSummary) -> Result<()> {
+        self.iter_mut().try_for_each(|reporter| reporter.scan_end(summary))
+    }
+}
+
+pub fn reporter<W: Write + Send + 'static>(format: OutputFormat, writer: W) -> Box<dyn Reporter> {
+    match format {
+        OutputFormat::Text => Box::new(BufferedReporter::new(writer, text::render, false)),
+        OutputFormat::Json => Box::new(BufferedReporter::new(writer, json::render, true)),
+        OutputFormat::Ndjson => Box::new(NdjsonReporter::new(writer)),
+        OutputFormat::Xml => Box::new(BufferedReporter::new(writer, xml::render, true)),
+        OutputFormat::Grepable => Box::new(BufferedReporter::new(writer, grepable::render, true)),
+        OutputFormat::Csv => Box::new(BufferedReporter::new(writer, csv::render, true)),
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HostReport {
+    pub ip: IpAddr,
+    pub zone: Option<Zone>,
+    pub hostname: Option<String>,
+    pub mac: Option<MacAddress>,
+    pub vendor: Option<String>,
+    pub ports: Vec<PortReport>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PortReport {
+    pub port: u16,
+    pub protocol: Protocol,
+    pub state: PortState,
+    pub service: Option<Service>,
+    pub banner: Option<String>,
+}
+
+impl ScanInfo {
+    pub fn command_line(&self) -> String {
+        self.command_line
+            .iter()
+            .map(|arg| shell_quote(arg))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    pub fn elapsed_seconds(&self, end_time: DateTime<Utc>) -> f64 {
+        (end_time - self.start_time).num_milliseconds() as f64 / 1000.0
+    }
+}
+
+impl From<&Target> for HostReport {
+    fn from(target: &Target) -> Self {
+        Self {
+            ip: target.ip,
+            zone: target.zone.clone(),
+            hostname: target.hostname.clone(),
+            mac: target.mac,
+            vendor: target.vendor.clone(),
+            ports: Vec::new(),
+        }
+    }
+}
+
+impl From<&ScanResult> for PortReport {
+    fn from(result: &ScanResult) -> Self {
+        Self {
+            port: result.port,
+            protocol: result.protocol,
+            state: result.state,
+            service: result.service.clone(),
+            banner: Some(result.raw_response.clone()).filter(|banner| !banner.is_empty()),
+        }
+    }
+}
+
+pub fn group_by_host(results: &[ScanResult], live_hosts: &[Target]) -> Vec<HostReport> {
+    let mut hosts: Vec<HostReport> = Vec::new();
+    let mut index: HashMap<(IpAddr, Option<Zone>, Option<String>), usize> = HashMap::new();
+
+    for target in live_hosts {
+        index.insert((target.ip, target.zone.clone(), target.hostname.clone()), hosts.len());
+        hosts.push(HostReport::from(target));
+    }
+
+    for result in results {
+        let key = (result.ip, result.zone.clone(), result.hostname.clone());
+        let position = *index.entry(key).or_insert_with(|| {
+            hosts.push(HostReport {
+                ip: result.ip,
+                zone: result.zone.clone(),
+                hostname: result.hostname.clone(),
+                mac: result.mac,
+                vendor: result.vendor.clone(),
+                ports: Vec::new(),
+            });
+            hosts.len() - 1
+        });
+        hosts[position].ports.push(PortReport::from(result));
+    }
+
+    for host in &mut hosts {
+        host.ports.sort_by_key(|port| (port.protocol, port.port));
+    }
+    hosts.sort_by(|a, b| {
+        (a.ip, a.zone.as_ref().map(|zone| zone.index), &a.hostname)
+            .cmp(&(b.ip, b.zone.as_ref().map(|zone| zone.index), &b.hostname))
+    });
+    hosts
+}
+
+#[derive(Serialize)]
+struct JsonStats {
+    hosts: usize,
+    ports: usize,
+    open_ports: usize,
+}
+
+#[derive(Debug, Default)]
+struct ReportBuffer {
+    info: Option<ScanInfo>,
+    results: Vec<ScanResult>,
+    live_hosts: Vec<Target>,
+}
+
+impl ReportBuffer {
+    fn start(&mut self, info: &ScanInfo) {
+        self.info = Some(info.clone());
+    }
+
+    fn info(&self) -> Result<&ScanInfo> {
+        self.info
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Scan ended before it was started"))
+    }
+
+    fn hosts(&self) -> Vec<HostReport> {
+        group_by_host(&self.results, &self.live_hosts)
+    }
+}
+
+type Render<W> = fn(&mut W, &ReportBuffer, &ScanSummary) -> Result<()>;
+
+struct BufferedReporter<W: Write> {
+    writer: W,
+    buffer: ReportBuffer,
+    render: Render<W>,
+    empty_hosts: bool,
+}
+
+impl<W: Write> BufferedReporter<W> {
+    fn new(writer: W, render: Render<W>, empty_hosts: bool) -> Self {
+        Self {
+            writer,
+            buffer: ReportBuffer::default(),
+            render,
+            empty_hosts,
+        }
+    }
+}
+
+impl<W: Write + Send> Reporter for BufferedReporter<W> {
+    fn scan_start(&mut self, info: &ScanInfo) -> Result<()> {
+        self.buffer.start(info);
+        Ok(())
+    }
+
+    fn host(&mut self, target: &Target) -> Result<()> {
+        if self.empty_hosts || self.buffer.info()?.ping_only {
+            self.buffer.live_hosts.push(target.clone());
+        }
+        Ok(())
+    }
+
+    fn port_result(&mut self, result: &ScanResult) -> Result<()> {
+        self.buffer.results.push(result.clone());
+        Ok(())
+    }
+
+    fn scan_end(&mut self, summary: &ScanSummary) -> Result<()> {
+        (self.render)(&mut self.writer, &self.buffer, summary)
+    }
+}
+
+fn timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+fn ctime(time: DateTime<Utc>) -> String {
+    time.format("%a %b %e %H:%M:%S %Y").to_string()
+}
+
+fn shell_quote(arg: &str) -> String {
+    let safe = !arg.is_empty()
+        && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_.,:/=@%+*".contains(c));
+    if safe {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
@@ -0,0 +1,132 @@
+use std::io::Write;
+use std::net::IpAddr;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use crate::types::{MacAddress, PortState, ScanResult, ScanType, Target, Zone};
+use super::{timestamp, JsonStats, PortReport, Reporter, ScanInfo, ScanSummary, JSON_FORMAT_VERSION};
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum NdjsonRecord<'a> {
+    ScanStart {
+        scanner: &'static str,
+        version: &'static str,
+        format_version: u32,
+        command_line: String,
+        start_time: String,
+        scan_types: &'a [ScanType],
+    },
+    Host {
+        time: String,
+        #[serde(flatten)]
+        host: HostFields<'a>,
+    },
+    Port {
+        time: String,
+        #[serde(flatten)]
+        host: HostFields<'a>,
+        #[serde(flatten)]
+        port: PortReport,
+    },
+    ScanEnd {
+        end_time: String,
+        elapsed_seconds: f64,
+        stats: JsonStats,
+    },
+}
+
+#[derive(Serialize)]
+struct HostFields<'a> {
+    ip: IpAddr,
+    zone: &'a Option<Zone>,
+    hostname: &'a Option<String>,
+    mac: Option<MacAddress>,
+    vendor: &'a Option<String>,
+}
+
+pub struct NdjsonReporter<W: Write> {
+    writer: W,
+    start_time: Option<DateTime<Utc>>,
+    hosts: usize,
+    ports: usize,
+    open_ports: usize,
+}
+
+impl<W: Write> NdjsonReporter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            start_time: None,
+            hosts: 0,
+            ports: 0,
+            open_ports: 0,
+        }
+    }
+
+    fn write_record(&mut self, record: &NdjsonRecord) -> Result<()> {
+        serde_json::to_writer(&mut self.writer, record)?;
+        writeln!(self.writer)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write + Send> Reporter for NdjsonReporter<W> {
+    fn scan_start(&mut self, info: &ScanInfo) -> Result<()> {
+        self.start_time = Some(info.start_time);
+        self.write_record(&NdjsonRecord::ScanStart {
+            scanner: env!("CARGO_PKG_NAME"),
+            version: env!("CARGO_PKG_VERSION"),
+            format_version: JSON_FORMAT_VERSION,
+            command_line: info.command_line(),
+            start_time: timestamp(info.start_time),
+            scan_types: &info.scan_types,
+        })
+    }
+
+    fn host(&mut self, target: &Target) -> Result<()> {
+        self.hosts += 1;
+        self.write_record(&NdjsonRecord::Host {
+            time: timestamp(Utc::now()),
+            host: HostFields {
+                ip: target.ip,
+                zone: &target.zone,
+                hostname: &target.hostname,
+                mac: target.mac,
+                vendor: &target.vendor,
+            },
+        })
+    }
+
+    fn port_result(&mut self, result: &ScanResult) -> Result<()> {
+        self.ports += 1;
+        if result.state == PortState::Open {
+            self.open_ports += 1;
+        }
+        self.write_record(&NdjsonRecord::Port {
+            time: timestamp(Utc::now()),
+            host: HostFields {
+                ip: result.ip,
+                zone: &result.zone,
+                hostname: &result.hostname,
+                mac: result.mac,
+                vendor: &result.vendor,
+            },
+            port: PortReport::from(result),
+        })
+    }
+
+    fn scan_end(&mut self, summary: &ScanSummary) -> Result<()> {
+        let start_time = self.start_time.unwrap_or(summary.end_time);
+        self.write_record(&NdjsonRecord::ScanEnd {
+            end_time: timestamp(summary.end_time),
+            elapsed_seconds: (summary.end_time - start_time).num_milliseconds() as f64 / 1000.0,
+            stats: JsonStats {
+                hosts: self.hosts,
+                ports: self.ports,
+                open_ports: self.open_ports,
+            },
+        })
+    }
+}
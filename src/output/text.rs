@@ -0,0 +1,34 @@
+use std::collections::HashMap;
+use std::io::Write;
+use anyhow::Result;
+use crate::utils::{format_live_host, format_scan_result};
+use super::{ReportBuffer, ScanSummary};
+
+pub(super) fn render<W: Write>(writer: &mut W, buffer: &ReportBuffer, _summary: &ScanSummary) -> Result<()> {
+    if buffer.info()?.ping_only {
+        writeln!(writer, "\nLive Hosts:")?;
+        for target in &buffer.live_hosts {
+            writeln!(writer, "{}", format_live_host(target))?;
+        }
+        writeln!(writer, "\n{} hosts up", buffer.live_hosts.len())?;
+    } else {
+        let mut service_stats: HashMap<String, u32> = HashMap::new();
+        for result in &buffer.results {
+            if let Some(service) = &result.service {
+                *service_stats.entry(service.name.clone()).or_insert(0) += 1;
+            }
+        }
+
+        writeln!(writer, "\nScan Results:")?;
+        for result in &buffer.results {
+            writeln!(writer, "{}", format_scan_result(result))?;
+        }
+
+        writeln!(writer, "\nService Statistics:")?;
+        for (service, count) in service_stats {
+            writeln!(writer, "  {}: {}", service, count)?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
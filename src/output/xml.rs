@@ -0,0 +1,207 @@
+use std::io::Write;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use crate::scanner::HostCounts;
+use crate::types::{PortState, Protocol, ScanType};
+use super::{ctime, HostReport, ReportBuffer, ScanInfo, ScanSummary, NMAP_XML_OUTPUT_VERSION};
+
+pub(super) fn render<W: Write>(writer: &mut W, buffer: &ReportBuffer, summary: &ScanSummary) -> Result<()> {
+    let hosts = buffer.hosts();
+    write_xml(writer, buffer.info()?, summary.end_time, &hosts, summary.hosts)
+}
+
+fn write_xml<W: Write>(
+    writer: &mut W,
+    info: &ScanInfo,
+    end_time: DateTime<Utc>,
+    hosts: &[HostReport],
+    counts: HostCounts,
+) -> Result<()> {
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(writer, "<!DOCTYPE nmaprun>")?;
+    writeln!(
+        writer,
+        r#"<nmaprun scanner="{}" args="{}" start="{}" startstr="{}" version="{}" xmloutputversion="{}">"#,
+        env!("CARGO_PKG_NAME"),
+        xml_escape(&info.command_line()),
+        info.start_time.timestamp(),
+        ctime(info.start_time),
+        env!("CARGO_PKG_VERSION"),
+        NMAP_XML_OUTPUT_VERSION,
+    )?;
+
+    for scan_type in &info.scan_types {
+        let (protocol, ports) = match scan_type {
+            ScanType::Connect | ScanType::Syn => (Protocol::Tcp, &info.ports.tcp),
+            ScanType::Udp => (Protocol::Udp, &info.ports.udp),
+        };
+        if ports.is_empty() {
+            continue;
+        }
+        writeln!(
+            writer,
+            r#"<scaninfo type="{}" protocol="{}" numservices="{}" services="{}"/>"#,
+            nmap_scan_type(*scan_type),
+            protocol,
+            ports.len(),
+            port_ranges(ports),
+        )?;
+    }
+    writeln!(writer, r#"<verbose level="0"/>"#)?;
+    writeln!(writer, r#"<debugging level="0"/>"#)?;
+
+    for host in hosts {
+        write_xml_host(writer, host)?;
+    }
+
+    let elapsed = info.elapsed_seconds(end_time);
+    writeln!(writer, "<runstats>")?;
+    writeln!(
+        writer,
+        r#"<finished time="{}" timestr="{}" summary="{} done at {}; {} IP address{} ({} host{} up) scanned in {:.2} seconds" elapsed="{:.2}" exit="success"/>"#,
+        end_time.timestamp(),
+        ctime(end_time),
+        env!("CARGO_PKG_NAME"),
+        ctime(end_time),
+        counts.up + counts.down,
+        if counts.up + counts.down == 1 { "" } else { "es" },
+        counts.up,
+        if counts.up == 1 { "" } else { "s" },
+        elapsed,
+        elapsed,
+    )?;
+    writeln!(
+        writer,
+        r#"<hosts up="{}" down="{}" total="{}"/>"#,
+        counts.up,
+        counts.down,
+        counts.up + counts.down,
+    )?;
+    writeln!(writer, "</runstats>")?;
+    writeln!(writer, "</nmaprun>")?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_xml_host<W: Write>(writer: &mut W, host: &HostReport) -> Result<()> {
+    let reason = if host.ip.is_loopback() {
+        "localhost-response"
+    } else if host.mac.is_some() {
+        "arp-response"
+    } else {
+        "unknown-response"
+    };
+    writeln!(writer, "<host>")?;
+    writeln!(writer, r#"<status state="up" reason="{}" reason_ttl="0"/>"#, reason)?;
+    writeln!(
+        writer,
+        r#"<address addr="{}" addrtype="{}"/>"#,
+        host.ip,
+        if host.ip.is_ipv4() { "ipv4" } else { "ipv6" },
+    )?;
+    if let Some(mac) = host.mac {
+        match &host.vendor {
+            Some(vendor) => writeln!(
+                writer,
+                r#"<address addr="{}" addrtype="mac" vendor="{}"/>"#,
+                mac,
+                xml_escape(vendor),
+            )?,
+            None => writeln!(writer, r#"<address addr="{}" addrtype="mac"/>"#, mac)?,
+        }
+    }
+    match &host.hostname {
+        Some(hostname) => {
+            writeln!(writer, "<hostnames>")?;
+            writeln!(writer, r#"<hostname name="{}" type="user"/>"#, xml_escape(hostname))?;
+            writeln!(writer, "</hostnames>")?;
+        }
+        None => writeln!(writer, "<hostnames/>")?,
+    }
+
+    if !host.ports.is_empty() {
+        writeln!(writer, "<ports>")?;
+        for port in &host.ports {
+            write!(writer, r#"<port protocol="{}" portid="{}">"#, port.protocol, port.port)?;
+            write!(
+                writer,
+                r#"<state state="{}" reason="{}" reason_ttl="0"/>"#,
+                port.state,
+                port_reason(port.protocol, port.state),
+            )?;
+            if let Some(service) = &port.service {
+                write!(writer, r#"<service name="{}""#, xml_escape(&service.name))?;
+                let attributes = [
+                    ("product", &service.product),
+                    ("version", &service.version),
+                    ("extrainfo", &service.extra_info),
+                    ("hostname", &service.hostname),
+                    ("ostype", &service.os_type),
+                    ("devicetype", &service.device_type),
+                ];
+                for (name, value) in attributes {
+                    if let Some(value) = value {
+                        write!(writer, r#" {}="{}""#, name, xml_escape(value))?;
+                    }
+                }
+                write!(writer, r#" method="probed" conf="10""#)?;
+                match &service.cpe {
+                    Some(cpe) => write!(writer, "><cpe>{}</cpe></service>", xml_escape(cpe))?,
+                    None => write!(writer, "/>")?,
+                }
+            }
+            writeln!(writer, "</port>")?;
+        }
+        writeln!(writer, "</ports>")?;
+    }
+    writeln!(writer, "</host>")?;
+    Ok(())
+}
+
+fn nmap_scan_type(scan_type: ScanType) -> &'static str {
+    match scan_type {
+        ScanType::Connect => "connect",
+        ScanType::Syn => "syn",
+        ScanType::Udp => "udp",
+    }
+}
+
+fn port_reason(protocol: Protocol, state: PortState) -> &'static str {
+    match (protocol, state) {
+        (Protocol::Tcp, PortState::Open) => "syn-ack",
+        (Protocol::Tcp, PortState::Closed) => "reset",
+        (Protocol::Udp, PortState::Open) => "udp-response",
+        (Protocol::Udp, PortState::Closed) => "port-unreach",
+        (_, PortState::Filtered | PortState::OpenFiltered) => "no-response",
+    }
+}
+
+fn port_ranges(ports: &[u16]) -> String {
+    let mut ranges: Vec<String> = Vec::new();
+    let mut iter = ports.iter().copied().peekable();
+    while let Some(start) = iter.next() {
+        let mut end = start;
+        while end < u16::MAX && iter.peek() == Some(&(end + 1)) {
+            end = iter.next().unwrap();
+        }
+        ranges.push(if start == end { start.to_string() } else { format!("{}-{}", start, end) });
+    }
+    ranges.join(",")
+}
+
+fn xml_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push_str(&format!("&#x{:X};", c as u32)),
+            c if c.is_control() => escaped.push_str(&format!("\\x{:02x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
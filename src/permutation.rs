@@ -0,0 +1,105 @@
+const FEISTEL_ROUNDS: usize = 4;
+
+#[derive(Debug, Clone)]
+pub struct Permutation {
+    range: u64,
+    half_bits: u32,
+    mask: u64,
+    keys: [u64; FEISTEL_ROUNDS],
+}
+
+impl Permutation {
+    pub fn new(range: u64, seed: u64) -> Self {
+        let bits = (64 - range.saturating_sub(1).leading_zeros()).max(2);
+        let half_bits = bits.div_ceil(2);
+        let mut state = seed;
+        let keys = std::array::from_fn(|_| {
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            splitmix64(state)
+        });
+
+        Self {
+            range,
+            half_bits,
+            mask: (1u64 << half_bits) - 1,
+            keys,
+        }
+    }
+
+    pub fn range(&self) -> u64 {
+        self.range
+    }
+
+    pub fn shuffle(&self, index: u64) -> u64 {
+        let mut value = index;
+        loop {
+            value = self.encrypt(value);
+            if value < self.range {
+                return value;
+            }
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = u64> + '_ {
+        (0..self.range).map(move |index| self.shuffle(index))
+    }
+
+    fn encrypt(&self, value: u64) -> u64 {
+        let mut left = (value >> self.half_bits) & self.mask;
+        let mut right = value & self.mask;
+        for key in &self.keys {
+            let next = left ^ (splitmix64(right ^ key) & self.mask);
+            left = right;
+            right = next;
+        }
+        (left << self.half_bits) | right
+    }
+}
+
+fn splitmix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_a_bijection_over_small_domains() {
+        for range in (0..=300).chain([1000, 4096, 4097]) {
+            for seed in [0, 1, 0xdead_beef] {
+                let mut seen = vec![false; range as usize];
+                for value in Permutation::new(range, seed).iter() {
+                    assert!(value < range, "range {} seed {} produced {}", range, seed, value);
+                    assert!(!seen[value as usize], "range {} seed {} repeated {}", range, seed, value);
+                    seen[value as usize] = true;
+                }
+                assert!(seen.iter().all(|&hit| hit), "range {} seed {} missed a value", range, seed);
+            }
+        }
+    }
+
+    #[test]
+    fn is_reproducible_per_seed() {
+        let first: Vec<u64> = Permutation::new(1000, 42).iter().collect();
+        let second: Vec<u64> = Permutation::new(1000, 42).iter().collect();
+        let other: Vec<u64> = Permutation::new(1000, 43).iter().collect();
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+        assert_ne!(first, (0..1000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn stays_in_range_for_large_domains() {
+        for range in [u64::from(u32::MAX) + 1, u64::MAX] {
+            let permutation = Permutation::new(range, 7);
+            assert_eq!(permutation.range(), range);
+            for index in [0, 1, 12345, range - 1] {
+                assert!(permutation.shuffle(index) < range);
+            }
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use std::collections::BTreeSet;
+use anyhow::{Result, Context};
+use crate::types::NmapService;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PortProtocol {
+    Any,
+    Tcp,
+    Udp,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PortList {
+    pub tcp: Vec<u16>,
+    pub udp: Vec<u16>,
+}
+
+impl PortList {
+    pub fn parse(spec: &str, services: &[NmapService]) -> Result<Self> {
+        let mut tcp = BTreeSet::new();
+        let mut udp = BTreeSet::new();
+        let mut protocol = PortProtocol::Any;
+
+        for item in spec.split(',') {
+            let mut item = item.trim();
+            if item.is_empty() {
+                continue;
+            }
+
+            if let Some((prefix, rest)) = item.split_once(':') {
+                protocol = match prefix.to_ascii_uppercase().as_str() {
+                    "T" => PortProtocol::Tcp,
+                    "U" => PortProtocol::Udp,
+                    _ => return Err(anyhow::anyhow!("Invalid protocol prefix '{}:' in port specification", prefix)),
+                };
+                item = rest.trim();
+                if item.is_empty() {
+                    continue;
+                }
+            }
+
+            if item.chars().all(|c| c.is_ascii_digit() || c == '-') {
+                let (start, end) = parse_port_item(item)?;
+                for port in start..=end {
+                    if protocol != PortProtocol::Udp {
+                        tcp.insert(port);
+                    }
+                    if protocol != PortProtocol::Tcp {
+                        udp.insert(port);
+                    }
+                }
+            } else {
+                let mut matched = false;
+                for service in services {
+                    if !glob_match(item, &service.name) {
+                        continue;
+                    }
+                    match (protocol, service.protocol.as_str()) {
+                        (PortProtocol::Any | PortProtocol::Tcp, "tcp") => {
+                            tcp.insert(service.port);
+                            matched = true;
+                        }
+                        (PortProtocol::Any | PortProtocol::Udp, "udp") => {
+                            udp.insert(service.port);
+                            matched = true;
+                        }
+                        _ => {}
+                    }
+                }
+                if !matched {
+                    return Err(anyhow::anyhow!("Unknown service name in port specification: {}", item));
+                }
+            }
+        }
+
+        if tcp.is_empty() && udp.is_empty() {
+            return Err(anyhow::anyhow!("Port specification '{}' contains no ports", spec));
+        }
+
+        Ok(Self {
+            tcp: tcp.into_iter().collect(),
+            udp: udp.into_iter().collect(),
+        })
+    }
+
+    pub fn top(services: &[NmapService], count: usize) -> Result<Self> {
+        if count == 0 {
+            return Err(anyhow::anyhow!("Number of top ports must be greater than zero"));
+        }
+        if services.is_empty() {
+            return Err(anyhow::anyhow!("nmap-services data is required for top ports mode"));
+        }
+
+        Ok(Self {
+            tcp: top_ports_for(services, "tcp", count),
+            udp: top_ports_for(services, "udp", count),
+        })
+    }
+}
+
+fn top_ports_for(services: &[NmapService], protocol: &str, count: usize) -> Vec<u16> {
+    let mut ranked: Vec<&NmapService> = services
+        .iter()
+        .filter(|s| s.protocol == protocol && s.port != 0)
+        .collect();
+    ranked.sort_by(|a, b| b.frequency.total_cmp(&a.frequency).then(a.port.cmp(&b.port)));
+    ranked.dedup_by_key(|s| s.port);
+
+    let mut ports: Vec<u16> = ranked.into_iter().take(count).map(|s| s.port).collect();
+    ports.sort_unstable();
+    ports
+}
+
+fn parse_port(value: &str) -> Result<u16> {
+    value.parse::<u16>()
+        .with_context(|| format!("Invalid port: {}", value))
+}
+
+fn parse_port_item(item: &str) -> Result<(u16, u16)> {
+    let (start, end) = match item.split_once('-') {
+        Some((start, end)) => {
+            let start = if start.is_empty() { 1 } else { parse_port(start)? };
+            let end = if end.is_empty() { u16::MAX } else { parse_port(end)? };
+            (start, end)
+        }
+        None => {
+            let port = parse_port(item)?;
+            (port, port)
+        }
+    };
+
+    if start > end {
+        return Err(anyhow::anyhow!("Start port cannot be greater than end port in '{}'", item));
+    }
+
+    Ok((start, end))
+}
+
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.as_bytes();
+    let name = name.as_bytes();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == b'?' || pattern[p].eq_ignore_ascii_case(&name[n])) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == b'*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = backtrack {
+            p = star_p + 1;
+            n = star_n + 1;
+            backtrack = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(name: &str, port: u16, protocol: &str) -> NmapService {
+        NmapService {
+            name: name.to_string(),
+            port,
+            protocol: protocol.to_string(),
+            frequency: 0.0,
+            description: None,
+        }
+    }
+
+    fn services() -> Vec<NmapService> {
+        vec![
+            service("http", 80, "tcp"),
+            service("http-alt", 8080, "tcp"),
+            service("https", 443, "tcp"),
+            service("domain", 53, "tcp"),
+            service("domain", 53, "udp"),
+            service("snmp", 161, "udp"),
+        ]
+    }
+
+    #[test]
+    fn parses_lists_and_ranges() {
+        let ports = PortList::parse("22,80,8000-8003,80", &[]).unwrap();
+        assert_eq!(ports.tcp, [22, 80, 8000, 8001, 8002, 8003]);
+        assert_eq!(ports.udp, ports.tcp);
+    }
+
+    #[test]
+    fn parses_open_ranges() {
+        let ports = PortList::parse("-3", &[]).unwrap();
+        assert_eq!(ports.tcp, [1, 2, 3]);
+
+        let ports = PortList::parse("65534-", &[]).unwrap();
+        assert_eq!(ports.tcp, [65534, 65535]);
+
+        let ports = PortList::parse("-", &[]).unwrap();
+        assert_eq!(ports.tcp.len(), 65535);
+    }
+
+    #[test]
+    fn protocol_prefix_carries_forward() {
+        let ports = PortList::parse("T:22,80,U:53,161,t:443", &[]).unwrap();
+        assert_eq!(ports.tcp, [22, 80, 443]);
+        assert_eq!(ports.udp, [53, 161]);
+    }
+
+    #[test]
+    fn resolves_service_names_and_globs() {
+        let ports = PortList::parse("http*,domain", &services()).unwrap();
+        assert_eq!(ports.tcp, [53, 80, 443, 8080]);
+        assert_eq!(ports.udp, [53]);
+
+        let ports = PortList::parse("U:domain,snmp,T:HTTP", &services()).unwrap();
+        assert_eq!(ports.tcp, [80]);
+        assert_eq!(ports.udp, [53, 161]);
+
+        let ports = PortList::parse("ht?p", &services()).unwrap();
+        assert_eq!(ports.tcp, [80]);
+    }
+
+    #[test]
+    fn rejects_invalid_specs() {
+        assert!(PortList::parse("100-10", &[]).is_err());
+        assert!(PortList::parse("70000", &[]).is_err());
+        assert!(PortList::parse("S:80", &[]).is_err());
+        assert!(PortList::parse("T:snmp", &services()).is_err());
+        assert!(PortList::parse("gopher", &services()).is_err());
+        assert!(PortList::parse(" , ", &[]).is_err());
+    }
+
+    #[test]
+    fn top_ports_rank_by_frequency() {
+        let mut services = services();
+        services[2].frequency = 0.5;
+        services[0].frequency = 0.4;
+        services[1].frequency = 0.1;
+        let ports = PortList::top(&services, 2).unwrap();
+        assert_eq!(ports.tcp, [80, 443]);
+        assert!(PortList::top(&services, 0).is_err());
+        assert!(PortList::top(&[], 10).is_err());
+    }
+}
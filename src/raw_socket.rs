@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::io;
+use std::mem::MaybeUninit;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use anyhow::Result;
+use socket2::{SockAddr, Socket};
+use tokio::sync::oneshot;
+use tokio::time::timeout;
+use crate::timing::Timing;
+
+const RECV_POLL_INTERVAL: Duration = Duration::from_millis(200);
+const RECV_BUFFER_LEN: usize = 65536;
+
+type Pending<K, V> = Arc<Mutex<HashMap<K, oneshot::Sender<V>>>>;
+
+pub struct Responder<K, V> {
+    pending: Pending<K, V>,
+    running: Arc<AtomicBool>,
+}
+
+impl<K, V> Default for Responder<K, V> {
+    fn default() -> Self {
+        Self {
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            running: Arc::new(AtomicBool::new(true)),
+        }
+    }
+}
+
+impl<K, V> Responder<K, V>
+where
+    K: Copy + Eq + Hash + Send + 'static,
+    V: Send + 'static,
+{
+    pub fn spawn_receiver<F>(&self, socket: Arc<Socket>, mut parse: F) -> io::Result<()>
+    where
+        F: FnMut(&[u8], &SockAddr) -> Option<(K, V)> + Send + 'static,
+    {
+        socket.set_read_timeout(Some(RECV_POLL_INTERVAL))?;
+        let pending = self.pending.clone();
+        let running = self.running.clone();
+
+        std::thread::spawn(move || {
+            let mut buffer = [MaybeUninit::<u8>::uninit(); RECV_BUFFER_LEN];
+            while running.load(Ordering::Relaxed) {
+                let (len, from) = match socket.recv_from(&mut buffer) {
+                    Ok(received) => received,
+                    Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut | io::ErrorKind::Interrupted) => continue,
+                    Err(_) => break,
+                };
+                // SAFETY: recv_from initialised the first `len` bytes of the buffer.
+                let packet = unsafe { std::slice::from_raw_parts(buffer.as_ptr() as *const u8, len) };
+
+                if let Some((key, reply)) = parse(packet, &from) {
+                    if let Some(tx) = pending.lock().unwrap().remove(&key) {
+                        let _ = tx.send(reply);
+                    }
+                }
+            }
+        });
+        Ok(())
+    }
+
+    pub async fn request<F>(&self, key: K, ip: IpAddr, timing: &Timing, mut send: F) -> Result<Option<V>>
+    where
+        F: FnMut() -> io::Result<usize>,
+    {
+        let (tx, mut rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(key, tx);
+
+        let mut reply = None;
+        for attempt in 0..=timing.retries_for(ip) {
+            timing.pace().await;
+            let started = Instant::now();
+            if let Err(e) = send() {
+                self.pending.lock().unwrap().remove(&key);
+                return Err(e.into());
+            }
+
+            match timeout(timing.timeout_for(ip), &mut rx).await {
+                Ok(Ok(value)) => {
+                    if attempt == 0 {
+                        timing.record(ip, started.elapsed());
+                    } else {
+                        timing.record_retransmitted(ip, attempt);
+                    }
+                    reply = Some(value);
+                    break;
+                }
+                Ok(Err(_)) => break,
+                Err(_) => continue,
+            }
+        }
+
+        self.pending.lock().unwrap().remove(&key);
+        if reply.is_none() {
+            timing.record_unanswered(ip);
+        }
+        Ok(reply)
+    }
+}
+
+impl<K, V> Drop for Responder<K, V> {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
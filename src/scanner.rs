@@ -1,104 +1,523 @@
-use std::net::{IpAddr, SocketAddr};
-use std::ops::RangeInclusive;
-use std::time::Duration;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::io::ErrorKind;
+use std::ops::ControlFlow;
+use std::time::Instant;
 use tokio::net::TcpStream;
 use tokio::time::timeout;
 use indicatif::{ProgressBar, ProgressStyle};
-use futures::stream::{self, StreamExt};
+use futures::future;
+use futures::stream::{self, Stream, StreamExt};
 use anyhow::Result;
-use crossbeam_channel::bounded;
-use crate::types::ScanResult;
-use crate::service_detection::detect_service;
+use chrono::Utc;
+use crossbeam_channel::{bounded, unbounded, Receiver};
+use crate::types::{ScanResult, ScanType, PortState, Protocol, Service, Target};
+use crate::ports::PortList;
+use crate::service_detection::ServiceProbes;
+use crate::udp::{probe_udp, UdpPayloads};
+use crate::syn::SynScanner;
+use crate::exclude::ExcludeList;
+use crate::permutation::Permutation;
+use crate::timing::{Timing, TimingConfig};
+use crate::discovery::HostDiscovery;
+use crate::output::{Reporter, ScanInfo, ScanSummary};
 
-const MAX_RETRIES: u32 = 2;
-const RETRY_DELAY: u64 = 500;
-const CHUNK_SIZE: usize = 1000;
-const SUBNET_CHUNK_SIZE: usize = 100;
+const RANDOMIZE_GROUP_SIZE: usize = 16384;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HostCounts {
+    pub up: usize,
+    pub down: usize,
+}
+
+#[derive(Default)]
+struct ProbeErrors {
+    count: AtomicUsize,
+    first: Mutex<Option<String>>,
+}
+
+impl ProbeErrors {
+    fn record(&self, error: &anyhow::Error) {
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.first.lock().unwrap().get_or_insert_with(|| format!("{:#}", error));
+    }
+
+    fn report(&self) {
+        let count = self.count.load(Ordering::Relaxed);
+        if let Some(first) = self.first.lock().unwrap().as_ref() {
+            eprintln!("Warning: {} probes failed with local errors and were not reported (first: {})", count, first);
+        }
+    }
+}
+
+enum ScanEvent {
+    Host(Target),
+    Port(Box<ScanResult>),
+}
 
 pub struct Scanner {
-    targets: Vec<IpAddr>,
-    port_range: RangeInclusive<u16>,
+    targets: Vec<Target>,
+    ports: PortList,
     concurrency: usize,
-    timeout: u64,
+    min_parallelism: usize,
     service_detection: bool,
+    service_probes: Arc<ServiceProbes>,
+    scan_types: Vec<ScanType>,
+    udp_payloads: UdpPayloads,
+    syn_scanner: Option<Arc<SynScanner>>,
+    report_closed: bool,
+    exclusions: ExcludeList,
+    target_count: Option<u64>,
+    randomize_seed: Option<u64>,
+    timing: TimingConfig,
+    max_rate: Option<f64>,
+    min_rate: Option<f64>,
+    discovery: Option<HostDiscovery>,
 }
 
 impl Scanner {
     pub fn new(
-        targets: Vec<IpAddr>,
-        port_range: RangeInclusive<u16>,
+        targets: Vec<Target>,
+        ports: PortList,
         concurrency: usize,
-        timeout: u64,
         service_detection: bool,
     ) -> Self {
         Self {
             targets,
-            port_range,
+            ports,
             concurrency,
-            timeout,
+            min_parallelism: 1,
             service_detection,
+            service_probes: Arc::new(ServiceProbes::default()),
+            scan_types: vec![ScanType::Connect],
+            udp_payloads: UdpPayloads::default(),
+            syn_scanner: None,
+            report_closed: false,
+            exclusions: ExcludeList::default(),
+            target_count: None,
+            randomize_seed: None,
+            timing: TimingConfig::default(),
+            max_rate: None,
+            min_rate: None,
+            discovery: None,
         }
     }
 
-    async fn try_connect(addr: SocketAddr, timeout_ms: u64) -> Result<Option<TcpStream>> {
-        for retry in 0..MAX_RETRIES {
-            match timeout(
-                Duration::from_millis(timeout_ms),
-                TcpStream::connect(addr),
-            ).await {
-                Ok(Ok(stream)) => return Ok(Some(stream)),
-                Ok(Err(_)) => return Ok(None),
-                Err(_) if retry < MAX_RETRIES - 1 => {
-                    tokio::time::sleep(Duration::from_millis(RETRY_DELAY)).await;
-                    continue;
+    pub fn with_scan_types(mut self, scan_types: Vec<ScanType>) -> Self {
+        self.scan_types = scan_types;
+        self
+    }
+
+    pub fn with_service_probes(mut self, service_probes: ServiceProbes) -> Self {
+        self.service_probes = Arc::new(service_probes);
+        self
+    }
+
+    pub fn with_udp_payloads(mut self, udp_payloads: UdpPayloads) -> Self {
+        self.udp_payloads = udp_payloads;
+        self
+    }
+
+    pub fn with_syn_scanner(mut self, syn_scanner: SynScanner) -> Self {
+        self.syn_scanner = Some(Arc::new(syn_scanner));
+        self
+    }
+
+    pub fn with_report_closed(mut self, report_closed: bool) -> Self {
+        self.report_closed = report_closed;
+        self
+    }
+
+    pub fn with_exclusions(mut self, exclusions: ExcludeList) -> Self {
+        self.exclusions = exclusions;
+        self
+    }
+
+    pub fn with_randomize_seed(mut self, seed: u64) -> Self {
+        self.randomize_seed = Some(seed);
+        self
+    }
+
+    pub fn with_timing(mut self, timing: TimingConfig) -> Self {
+        self.timing = timing;
+        self
+    }
+
+    pub fn with_max_rate(mut self, max_rate: f64) -> Self {
+        self.max_rate = Some(max_rate);
+        self
+    }
+
+    pub fn with_min_rate(mut self, min_rate: f64) -> Self {
+        self.min_rate = Some(min_rate);
+        self
+    }
+
+    pub fn with_discovery(mut self, discovery: HostDiscovery) -> Self {
+        self.discovery = Some(discovery);
+        self
+    }
+
+    pub fn with_min_parallelism(mut self, min_parallelism: usize) -> Self {
+        self.min_parallelism = min_parallelism;
+        self
+    }
+
+    fn parallelism(&self) -> (usize, usize) {
+        let max = match self.timing.max_parallelism {
+            Some(max_parallelism) => self.concurrency.min(max_parallelism),
+            None => self.concurrency,
+        };
+        let min = self.min_parallelism.min(max);
+        match self.min_rate {
+            Some(min_rate) => {
+                let attempts = self.timing.max_retries.min(1) + 1;
+                let worst_case_secs = self.timing.initial_rtt_timeout.as_secs_f64() * attempts as f64;
+                let needed = (min_rate * worst_case_secs).ceil() as usize;
+                if needed > max {
+                    eprintln!(
+                        "Warning: --min-rate {} needs about {} parallel probes, but parallelism is capped at {}; the rate may not be reached",
+                        min_rate, needed, max,
+                    );
                 }
-                Err(_) => return Ok(None),
+                (min.max(needed.min(max)), max)
             }
+            None => (min, max),
         }
-        Ok(None)
     }
 
-    async fn scan_addr(&self, addr: SocketAddr) -> Result<Option<ScanResult>> {
-        if let Ok(Some(mut stream)) = Self::try_connect(addr, self.timeout).await {
-            let mut service = None;
-            let mut raw_response = String::new();
+    pub fn with_target_count(mut self, target_count: u64) -> Self {
+        self.target_count = Some(target_count);
+        self
+    }
 
-            if self.service_detection {
-                if let Ok((detected_service, response)) = detect_service(&mut stream).await {
-                    service = detected_service;
-                    raw_response = response;
-                }
-            }
+    fn ports_for(&self, scan_type: ScanType) -> &[u16] {
+        match scan_type.protocol() {
+            Protocol::Tcp => &self.ports.tcp,
+            Protocol::Udp => &self.ports.udp,
+        }
+    }
 
-            Ok(Some(ScanResult {
-                ip: addr.ip(),
-                port: addr.port(),
-                service,
-                raw_response,
-            }))
+    fn addrs_per_ip(&self) -> usize {
+        self.scan_types.iter().map(|&t| self.ports_for(t).len()).sum()
+    }
+
+    fn should_report(&self, state: PortState) -> bool {
+        self.report_closed || matches!(state, PortState::Open | PortState::OpenFiltered)
+    }
+
+    async fn detect(&self, timing: &Timing, addr: SocketAddr, stream: TcpStream) -> (Option<Service>, String) {
+        let (service, response) = self.service_probes.detect_tcp(addr, stream, timing).await;
+        (service, String::from_utf8_lossy(&response).to_string())
+    }
+
+    async fn scan_addr(&self, timing: &Timing, scan_type: ScanType, addr: SocketAddr) -> Result<Option<ScanResult>> {
+        match scan_type {
+            ScanType::Connect => self.scan_tcp_connect(timing, addr).await,
+            ScanType::Syn => match &self.syn_scanner {
+                Some(syn_scanner) if syn_scanner.supports(addr.ip()) => self.scan_syn(timing, syn_scanner, addr).await,
+                _ => self.scan_tcp_connect(timing, addr).await,
+            },
+            ScanType::Udp => self.scan_udp(timing, addr).await,
+        }
+    }
+
+    async fn scan_tcp_connect(&self, timing: &Timing, addr: SocketAddr) -> Result<Option<ScanResult>> {
+        let (state, stream) = try_connect(addr, timing).await?;
+        if !self.should_report(state) {
+            return Ok(None);
+        }
+
+        let (service, raw_response) = match stream {
+            Some(stream) if self.service_detection => self.detect(timing, addr, stream).await,
+            _ => (None, String::new()),
+        };
+
+        Ok(Some(ScanResult {
+            ip: addr.ip(),
+            zone: None,
+            hostname: None,
+            mac: None,
+            vendor: None,
+            port: addr.port(),
+            protocol: Protocol::Tcp,
+            state,
+            service,
+            raw_response,
+        }))
+    }
+
+    async fn scan_syn(&self, timing: &Timing, syn_scanner: &SynScanner, addr: SocketAddr) -> Result<Option<ScanResult>> {
+        let state = syn_scanner.probe(addr, timing).await?;
+        if !self.should_report(state) {
+            return Ok(None);
+        }
+
+        let (service, raw_response) = if state == PortState::Open && self.service_detection {
+            match try_connect(addr, timing).await? {
+                (_, Some(stream)) => self.detect(timing, addr, stream).await,
+                _ => (None, String::new()),
+            }
         } else {
-            Ok(None)
+            (None, String::new())
+        };
+
+        Ok(Some(ScanResult {
+            ip: addr.ip(),
+            zone: None,
+            hostname: None,
+            mac: None,
+            vendor: None,
+            port: addr.port(),
+            protocol: Protocol::Tcp,
+            state,
+            service,
+            raw_response,
+        }))
+    }
+
+    async fn scan_udp(&self, timing: &Timing, addr: SocketAddr) -> Result<Option<ScanResult>> {
+        let payload = self.udp_payloads.get(addr.port());
+        let (state, response) = probe_udp(addr, payload, timing).await?;
+
+        if !self.should_report(state) {
+            return Ok(None);
         }
+
+        let raw_response = String::from_utf8_lossy(&response).to_string();
+        let service = if self.service_detection && !response.is_empty() {
+            self.service_probes.match_response(Protocol::Udp, addr.port(), &response)
+        } else {
+            None
+        };
+
+        Ok(Some(ScanResult {
+            ip: addr.ip(),
+            zone: None,
+            hostname: None,
+            mac: None,
+            vendor: None,
+            port: addr.port(),
+            protocol: Protocol::Udp,
+            state,
+            service,
+            raw_response,
+        }))
     }
 
-    async fn scan_ip_chunk(&self, ips: &[IpAddr]) -> Vec<ScanResult> {
+    fn probes_for(&self, target: Target) -> impl Iterator<Item = (ScanType, SocketAddr, Arc<Target>)> + '_ {
+        let target = Arc::new(target);
+        self.scan_types.iter().flat_map(move |&scan_type| {
+            let target = target.clone();
+            self.ports_for(scan_type)
+                .iter()
+                .map(move |&port| (scan_type, target.socket_addr(port), target.clone()))
+        })
+    }
+
+    fn randomized_probes<'a, S>(&'a self, targets: S, seed: u64) -> impl Stream<Item = (ScanType, SocketAddr, Arc<Target>)> + 'a
+    where
+        S: Stream<Item = Target> + 'a,
+    {
+        let templates: Arc<Vec<(ScanType, u16)>> = Arc::new(
+            self.scan_types
+                .iter()
+                .flat_map(|&scan_type| self.ports_for(scan_type).iter().map(move |&port| (scan_type, port)))
+                .collect(),
+        );
+
+        targets
+            .chunks(RANDOMIZE_GROUP_SIZE)
+            .enumerate()
+            .flat_map(move |(group, hosts)| {
+                let hosts: Vec<Arc<Target>> = hosts.into_iter().map(Arc::new).collect();
+                let templates = templates.clone();
+                let per_host = templates.len() as u64;
+                let permutation = Permutation::new(hosts.len() as u64 * per_host, seed.wrapping_add(group as u64));
+
+                stream::iter((0..permutation.range()).map(move |index| {
+                    let index = permutation.shuffle(index);
+                    let target = &hosts[(index / per_host) as usize];
+                    let (scan_type, port) = templates[(index % per_host) as usize];
+                    (scan_type, target.socket_addr(port), target.clone())
+                }))
+            })
+    }
+
+    pub async fn run(&self) -> Vec<ScanResult> {
+        let total_ips = self.targets
+            .iter()
+            .filter(|target| !self.exclusions.contains(target.ip))
+            .count();
+        let total_addrs = self.addrs_per_ip() * total_ips;
+
+        eprintln!("Total addresses to scan: {}", total_addrs);
+
         let mut results = Vec::new();
-        let mut addrs = Vec::new();
+        self.scan_stream(stream::iter(self.targets.clone()), Some(total_addrs as u64), |event| {
+            if let ScanEvent::Port(result) = event {
+                results.push(*result);
+            }
+            ControlFlow::Continue(())
+        })
+        .await;
+        results
+    }
 
-        for &ip in ips {
-            for port in self.port_range.clone() {
-                addrs.push(SocketAddr::new(ip, port));
+    pub async fn run_stream<S>(&self, targets: S) -> Vec<ScanResult>
+    where
+        S: Stream<Item = Target>,
+    {
+        let mut results = Vec::new();
+        self.run_stream_with(targets, |result| results.push(result)).await;
+        results
+    }
+
+    pub async fn run_stream_with<S, F>(&self, targets: S, mut on_result: F) -> HostCounts
+    where
+        S: Stream<Item = Target>,
+        F: FnMut(ScanResult),
+    {
+        self.scan_events(targets, |event| {
+            if let ScanEvent::Port(result) = event {
+                on_result(*result);
             }
+            ControlFlow::Continue(())
+        })
+        .await
+    }
+
+    pub async fn report_stream<S>(&self, targets: S, info: &ScanInfo, reporter: &mut dyn Reporter) -> Result<HostCounts>
+    where
+        S: Stream<Item = Target>,
+    {
+        reporter.scan_start(info)?;
+        let mut error = None;
+        let counts = self.scan_events(targets, |event| {
+            let written = match &event {
+                ScanEvent::Host(target) => reporter.host(target),
+                ScanEvent::Port(result) => reporter.port_result(result),
+            };
+            stop_on_error(written, &mut error)
+        }).await;
+        finish_report(reporter, error, counts)
+    }
+
+    pub async fn report_ping_sweep<S>(&self, targets: S, info: &ScanInfo, reporter: &mut dyn Reporter) -> Result<HostCounts>
+    where
+        S: Stream<Item = Target>,
+    {
+        reporter.scan_start(info)?;
+        let mut error = None;
+        let counts = self.ping_sweep(targets, |target| stop_on_error(reporter.host(&target), &mut error)).await;
+        finish_report(reporter, error, counts)
+    }
+
+    pub async fn run_ping_sweep<S>(&self, targets: S) -> Vec<Target>
+    where
+        S: Stream<Item = Target>,
+    {
+        let mut live_hosts = Vec::new();
+        self.run_ping_sweep_with(targets, |target| live_hosts.push(target)).await;
+        live_hosts
+    }
+
+    pub async fn run_ping_sweep_with<S, F>(&self, targets: S, mut on_host: F) -> HostCounts
+    where
+        S: Stream<Item = Target>,
+        F: FnMut(Target),
+    {
+        self.ping_sweep(targets, |target| {
+            on_host(target);
+            ControlFlow::Continue(())
+        })
+        .await
+    }
+
+    async fn ping_sweep<S, F>(&self, targets: S, mut on_host: F) -> HostCounts
+    where
+        S: Stream<Item = Target>,
+        F: FnMut(Target) -> ControlFlow<()>,
+    {
+        let (min_parallelism, max_parallelism) = self.parallelism();
+        let timing = Timing::new(self.timing.clone(), self.max_rate, min_parallelism, max_parallelism);
+        let excluded = AtomicUsize::new(0);
+        let errors = ProbeErrors::default();
+        let progress_bar = new_progress_bar(self.target_count, "hosts");
+
+        let targets = stream::iter(self.targets.clone())
+            .chain(targets)
+            .filter(|target| {
+                let keep = !self.exclusions.contains(target.ip);
+                if !keep {
+                    excluded.fetch_add(1, Ordering::Relaxed);
+                    progress_bar.inc(1);
+                }
+                future::ready(keep)
+            });
+
+        let targets = match self.randomize_seed {
+            Some(seed) => randomized_targets(targets, seed).left_stream(),
+            None => targets.right_stream(),
+        };
+
+        let mut counts = HostCounts::default();
+        let mut live_hosts = std::pin::pin!(match &self.discovery {
+            Some(discovery) => discover_hosts(discovery, targets, &timing, max_parallelism, &errors)
+                .filter_map(|(target, alive)| {
+                    progress_bar.set_message(format!("{}", target.ip));
+                    progress_bar.inc(1);
+                    if !alive {
+                        counts.down += 1;
+                    }
+                    future::ready(alive.then_some(target))
+                })
+                .left_stream(),
+            None => targets.right_stream(),
+        });
+        while let Some(target) = live_hosts.next().await {
+            counts.up += 1;
+            if on_host(target).is_break() {
+                break;
+            }
+        }
+
+        progress_bar.finish_and_clear();
+
+        if !self.exclusions.is_empty() {
+            eprintln!("Excluded {} addresses", excluded.load(Ordering::Relaxed));
         }
+        errors.report();
+
+        counts
+    }
+
+    async fn scan_events<S, F>(&self, targets: S, on_event: F) -> HostCounts
+    where
+        S: Stream<Item = Target>,
+        F: FnMut(ScanEvent) -> ControlFlow<()>,
+    {
+        let targets = stream::iter(self.targets.clone()).chain(targets);
+        let total_addrs = self.target_count.map(|count| count * self.addrs_per_ip() as u64);
+        self.scan_stream(targets, total_addrs, on_event).await
+    }
+
+    async fn scan_stream<S, F>(&self, targets: S, total_addrs: Option<u64>, mut on_event: F) -> HostCounts
+    where
+        S: Stream<Item = Target>,
+        F: FnMut(ScanEvent) -> ControlFlow<()>,
+    {
+        let excluded = AtomicUsize::new(0);
+        let hosts_up = AtomicUsize::new(0);
+        let hosts_down = AtomicUsize::new(0);
+        let errors = ProbeErrors::default();
+        let (host_tx, host_rx) = unbounded::<Target>();
+        let (min_parallelism, max_parallelism) = self.parallelism();
+        let timing = Timing::new(self.timing.clone(), self.max_rate, min_parallelism, max_parallelism);
 
         let (progress_tx, progress_rx) = bounded::<(SocketAddr, bool)>(1000);
-        let progress_bar = ProgressBar::new(addrs.len() as u64);
-        progress_bar.set_style(
-            ProgressStyle::default_bar()
-                .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta}) Scanning {msg}")
-                .unwrap()
-                .progress_chars("#>-"),
-        );
+        let progress_bar = new_progress_bar(total_addrs, "probes");
 
         let progress_bar_clone = progress_bar.clone();
         std::thread::spawn(move || {
@@ -108,47 +527,132 @@ impl Scanner {
             }
         });
 
-        let mut stream = stream::iter(addrs)
-            .map(|addr| {
+        let addrs_per_ip = self.addrs_per_ip() as u64;
+        let targets = targets
+            .filter(|target| {
+                let keep = !self.exclusions.contains(target.ip);
+                if !keep {
+                    excluded.fetch_add(1, Ordering::Relaxed);
+                    progress_bar.inc(addrs_per_ip);
+                }
+                future::ready(keep)
+            });
+
+        let targets = match self.randomize_seed {
+            Some(seed) if self.discovery.is_some() => randomized_targets(targets, seed).left_stream(),
+            _ => targets.right_stream(),
+        };
+
+        let targets = match &self.discovery {
+            Some(discovery) => discover_hosts(discovery, targets, &timing, max_parallelism, &errors)
+                .filter_map(|(target, alive)| {
+                    if alive {
+                        hosts_up.fetch_add(1, Ordering::Relaxed);
+                    } else {
+                        hosts_down.fetch_add(1, Ordering::Relaxed);
+                        progress_bar.inc(addrs_per_ip);
+                    }
+                    future::ready(alive.then_some(target))
+                })
+                .left_stream(),
+            None => targets
+                .inspect(|_| {
+                    hosts_up.fetch_add(1, Ordering::Relaxed);
+                })
+                .right_stream(),
+        };
+
+        let targets = match &self.discovery {
+            Some(_) => targets
+                .inspect(move |target| {
+                    let _ = host_tx.send(target.clone());
+                })
+                .left_stream(),
+            None => targets.right_stream(),
+        };
+
+        let probes = match self.randomize_seed {
+            Some(seed) => self.randomized_probes(targets, seed).left_stream(),
+            None => targets
+                .flat_map(|target| stream::iter(self.probes_for(target)))
+                .right_stream(),
+        };
+
+        let mut stream = std::pin::pin!(probes
+            .map(|(scan_type, addr, target)| {
                 let progress_tx = progress_tx.clone();
+                let timing = &timing;
                 async move {
-                    let result = self.scan_addr(addr).await;
+                    let slot = timing.congestion().acquire().await;
+                    let result = self.scan_addr(timing, scan_type, addr).await.map(|result| {
+                        result.map(|scan_result| ScanResult {
+                            zone: target.zone.clone(),
+                            hostname: target.hostname.clone(),
+                            mac: target.mac,
+                            vendor: target.vendor.clone(),
+                            ..scan_result
+                        })
+                    });
+                    drop(slot);
                     let _ = progress_tx.send((addr, result.is_ok()));
                     result
                 }
             })
-            .buffer_unordered(self.concurrency);
+            .buffer_unordered(max_parallelism));
 
+        let mut stopped = false;
         while let Some(result) = stream.next().await {
-            if let Ok(Some(scan_result)) = result {
-                results.push(scan_result);
+            if emit_hosts(&host_rx, &mut on_event).is_break() {
+                stopped = true;
+                break;
+            }
+            let flow = match result {
+                Ok(Some(scan_result)) => on_event(ScanEvent::Port(Box::new(scan_result))),
+                Ok(None) => ControlFlow::Continue(()),
+                Err(e) => {
+                    errors.record(&e);
+                    ControlFlow::Continue(())
+                }
+            };
+            if flow.is_break() {
+                stopped = true;
+                break;
             }
         }
+        if !stopped {
+            let _ = emit_hosts(&host_rx, &mut on_event);
+        }
 
         progress_bar.finish_and_clear();
-        results
-    }
 
-    pub async fn run(&self) -> Vec<ScanResult> {
-        let mut all_results = Vec::new();
-        let total_ips = self.targets.len();
-        let total_ports = self.port_range.end() - self.port_range.start() + 1;
-        let total_addrs = (total_ports as usize) * total_ips;
-
-        println!("Total addresses to scan: {}", total_addrs);
+        if !self.exclusions.is_empty() {
+            eprintln!("Excluded {} addresses", excluded.load(Ordering::Relaxed));
+        }
+        if self.discovery.is_some() {
+            eprintln!(
+                "Host discovery: {} hosts up, {} hosts down",
+                hosts_up.load(Ordering::Relaxed),
+                hosts_down.load(Ordering::Relaxed),
+            );
+        }
+        errors.report();
 
-        let chunk_size = if total_ips > 1 {
-            SUBNET_CHUNK_SIZE
-        } else {
-            CHUNK_SIZE
-        };
+        let stats = timing.congestion().stats();
+        eprintln!(
+            "Parallelism: {} final, {} peak (bounds {}-{}), {} backoffs after {} retransmitted replies and {} unanswered probes",
+            stats.final_parallelism,
+            stats.peak_parallelism,
+            stats.min_parallelism,
+            stats.max_parallelism,
+            stats.backoffs,
+            stats.retransmitted,
+            stats.unanswered,
+        );
 
-        for chunk in self.targets.chunks(chunk_size) {
-            let chunk_results = self.scan_ip_chunk(chunk).await;
-            all_results.extend(chunk_results);
+        HostCounts {
+            up: hosts_up.load(Ordering::Relaxed),
+            down: hosts_down.load(Ordering::Relaxed),
         }
-
-        all_results
     }
 }
 
@@ -156,10 +660,148 @@ impl Clone for Scanner {
     fn clone(&self) -> Self {
         Self {
             targets: self.targets.clone(),
-            port_range: self.port_range.clone(),
+            ports: self.ports.clone(),
             concurrency: self.concurrency,
-            timeout: self.timeout,
+            min_parallelism: self.min_parallelism,
             service_detection: self.service_detection,
+            service_probes: self.service_probes.clone(),
+            scan_types: self.scan_types.clone(),
+            udp_payloads: self.udp_payloads.clone(),
+            syn_scanner: self.syn_scanner.clone(),
+            report_closed: self.report_closed,
+            exclusions: self.exclusions.clone(),
+            target_count: self.target_count,
+            randomize_seed: self.randomize_seed,
+            timing: self.timing.clone(),
+            max_rate: self.max_rate,
+            min_rate: self.min_rate,
+            discovery: self.discovery.clone(),
+        }
+    }
+}
+
+fn new_progress_bar(total: Option<u64>, unit: &str) -> ProgressBar {
+    match total {
+        Some(total) => {
+            let progress_bar = ProgressBar::new(total);
+            progress_bar.set_style(
+                ProgressStyle::default_bar()
+                    .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({eta}) Scanning {msg}")
+                    .unwrap()
+                    .progress_chars("#>-"),
+            );
+            progress_bar
+        }
+        None => {
+            let progress_bar = ProgressBar::new_spinner();
+            progress_bar.set_style(
+                ProgressStyle::default_spinner()
+                    .template(&format!("{{spinner:.green}} [{{elapsed_precise}}] {{pos}} {} ({{per_sec}}) Scanning {{msg}}", unit))
+                    .unwrap(),
+            );
+            progress_bar
+        }
+    }
+}
+
+fn randomized_targets<S>(targets: S, seed: u64) -> impl Stream<Item = Target>
+where
+    S: Stream<Item = Target>,
+{
+    targets
+        .chunks(RANDOMIZE_GROUP_SIZE)
+        .enumerate()
+        .flat_map(move |(group, hosts)| {
+            let permutation = Permutation::new(hosts.len() as u64, seed.rotate_left(32).wrapping_add(group as u64));
+            let mut hosts: Vec<Option<Target>> = hosts.into_iter().map(Some).collect();
+            stream::iter((0..permutation.range()).filter_map(move |index| {
+                hosts[permutation.shuffle(index) as usize].take()
+            }))
+        })
+}
+
+fn discover_hosts<'a, S>(
+    discovery: &'a HostDiscovery,
+    targets: S,
+    timing: &'a Timing,
+    max_parallelism: usize,
+    errors: &'a ProbeErrors,
+) -> impl Stream<Item = (Target, bool)> + 'a
+where
+    S: Stream<Item = Target> + 'a,
+{
+    let width = (max_parallelism / discovery.probes_per_host().max(1)).max(1);
+    targets
+        .map(move |target| discovery.discover(target, timing))
+        .buffer_unordered(width)
+        .map(move |(target, alive)| {
+            let alive = alive.unwrap_or_else(|e| {
+                errors.record(&e);
+                false
+            });
+            (target, alive)
+        })
+}
+
+fn emit_hosts<F>(live_hosts: &Receiver<Target>, on_event: &mut F) -> ControlFlow<()>
+where
+    F: FnMut(ScanEvent) -> ControlFlow<()>,
+{
+    live_hosts.try_iter().try_for_each(|target| on_event(ScanEvent::Host(target)))
+}
+
+fn stop_on_error(written: Result<()>, error: &mut Option<anyhow::Error>) -> ControlFlow<()> {
+    match written {
+        Ok(()) => ControlFlow::Continue(()),
+        Err(e) => {
+            *error = Some(e);
+            ControlFlow::Break(())
         }
     }
-} 
+}
+
+fn finish_report(reporter: &mut dyn Reporter, error: Option<anyhow::Error>, hosts: HostCounts) -> Result<HostCounts> {
+    if let Some(e) = error {
+        return Err(e.context("Failed to write results"));
+    }
+    reporter.scan_end(&ScanSummary {
+        end_time: Utc::now(),
+        hosts,
+    })?;
+    Ok(hosts)
+}
+
+pub async fn try_connect(addr: SocketAddr, timing: &Timing) -> Result<(PortState, Option<TcpStream>)> {
+    for attempt in 0..=timing.retries_for(addr.ip()) {
+        timing.pace().await;
+        let started = Instant::now();
+        let result = match timeout(timing.timeout_for(addr.ip()), TcpStream::connect(addr)).await {
+            Ok(result) => result,
+            Err(_) => continue,
+        };
+
+        let state = match &result {
+            Ok(_) => PortState::Open,
+            Err(e) => classify_connect_error(e)
+                .ok_or_else(|| anyhow::anyhow!("Failed to connect to {}: {}", addr, e))?,
+        };
+        if state == PortState::Filtered {
+            timing.record_unanswered(addr.ip());
+        } else if attempt == 0 {
+            timing.record(addr.ip(), started.elapsed());
+        } else {
+            timing.record_retransmitted(addr.ip(), attempt);
+        }
+        return Ok((state, result.ok()));
+    }
+    timing.record_unanswered(addr.ip());
+    Ok((PortState::Filtered, None))
+}
+
+fn classify_connect_error(error: &std::io::Error) -> Option<PortState> {
+    match error.kind() {
+        ErrorKind::ConnectionRefused | ErrorKind::ConnectionReset => Some(PortState::Closed),
+        ErrorKind::TimedOut | ErrorKind::HostUnreachable | ErrorKind::NetworkUnreachable => Some(PortState::Filtered),
+        _ => None,
+    }
+}
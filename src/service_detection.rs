@@ -1,89 +1,272 @@
-use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+use regex::bytes::{Captures, Regex};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
+use tokio::time::{timeout_at, Instant};
 use anyhow::Result;
-use crate::types::Service;
-use crate::patterns::get_all_patterns;
-
-pub async fn detect_service(stream: &mut TcpStream) -> Result<(Option<Service>, String)> {
-    let mut buffer = [0u8; 1024];
-    let mut raw_response = String::new();
-    let patterns = get_all_patterns();    
-    let mut probe = Vec::new();
-    for pattern in &patterns {
-        if !pattern.probe.is_empty() {
-            probe.extend_from_slice(pattern.probe.as_bytes());
-        }
-    }
+use crate::nmap_regex;
+use crate::nmap_template;
+use crate::patterns::load_nmap_probes;
+use crate::scanner::try_connect;
+use crate::timing::Timing;
+use crate::types::{NmapMatch, NmapProbe, Protocol, Service};
 
-    stream.write_all(&probe).await?;
-    stream.flush().await?;
+const VERSION_INTENSITY: u8 = 2;
+const MAX_RESPONSE_SIZE: usize = 16384;
+const TCP_WRAPPED: &str = "tcpwrapped";
 
-    let mut response = Vec::new();
-    let mut total_read = 0;
-    let timeout = std::time::Duration::from_secs(2);
+struct ServiceMatch {
+    regex: Regex,
+    info: NmapMatch,
+}
 
-    while total_read < buffer.len() {
-        match tokio::time::timeout(timeout, stream.read(&mut buffer[total_read..])).await {
-            Ok(Ok(0)) => break,
-            Ok(Ok(n)) => {
-                total_read += n;
-                response.extend_from_slice(&buffer[..n]);
-            }
-            Ok(Err(e)) if e.kind() == io::ErrorKind::WouldBlock => break,
-            Ok(Err(e)) => return Err(e.into()),
-            Err(_) => break,
+struct ServiceProbe {
+    probe: NmapProbe,
+    matches: Vec<ServiceMatch>,
+    fallback: Vec<usize>,
+}
+
+enum MatchResult {
+    Hard(Service),
+    Soft(String),
+    None,
+}
+
+#[derive(Default)]
+pub struct ServiceProbes {
+    probes: Vec<ServiceProbe>,
+    loaded: usize,
+    unsupported: usize,
+}
+
+impl ServiceProbes {
+    pub fn load(file_path: &str) -> Result<Self> {
+        Ok(Self::from_probes(load_nmap_probes(file_path)?))
+    }
+
+    pub fn from_probes(probes: Vec<NmapProbe>) -> Self {
+        let mut loaded = 0;
+        let mut unsupported = 0;
+        let mut compiled: Vec<ServiceProbe> = probes
+            .into_iter()
+            .map(|mut probe| {
+                let matches = std::mem::take(&mut probe.matches)
+                    .into_iter()
+                    .filter_map(|info| match nmap_regex::compile(&info) {
+                        Ok(regex) => {
+                            loaded += 1;
+                            Some(ServiceMatch { regex, info })
+                        }
+                        Err(_) => {
+                            unsupported += 1;
+                            None
+                        }
+                    })
+                    .collect();
+                ServiceProbe { probe, matches, fallback: Vec::new() }
+            })
+            .collect();
+
+        for index in 0..compiled.len() {
+            let probe = &compiled[index].probe;
+            let fallback = probe.fallback
+                .iter()
+                .filter_map(|name| {
+                    compiled.iter().position(|other| {
+                        other.probe.name == *name && other.probe.protocol == probe.protocol
+                    })
+                })
+                .collect();
+            compiled[index].fallback = fallback;
         }
+
+        Self { probes: compiled, loaded, unsupported }
     }
 
-    if !response.is_empty() {
-        raw_response = String::from_utf8_lossy(&response).to_string();
+    pub fn loaded(&self) -> usize {
+        self.loaded
     }
 
-    for pattern in patterns {
-        if pattern.regex.is_match(&raw_response) {
-            let mut service = Service {
-                name: pattern.name.clone(),
-                version: None,
-                product: None,
-                os_type: None,
-                extra_info: None,
-                cpe: None,
+    pub fn unsupported(&self) -> usize {
+        self.unsupported
+    }
+
+    pub async fn detect_tcp(&self, addr: SocketAddr, stream: TcpStream, timing: &Timing) -> (Option<Service>, Vec<u8>) {
+        let mut stream = Some(stream);
+        let mut soft_match: Option<String> = None;
+        let mut banner = Vec::new();
+
+        for index in self.probe_order("TCP", addr.port()) {
+            let probe = &self.probes[index];
+            if let Some(service) = &soft_match {
+                if !probe.matches.iter().any(|m| m.info.service == *service) {
+                    continue;
+                }
+            }
+
+            let stream = match stream.take() {
+                Some(stream) => stream,
+                None => match try_connect(addr, timing).await {
+                    Ok((_, Some(stream))) => stream,
+                    _ => break,
+                },
             };
 
-            if let Some(version_regex) = pattern.version_regex {
-                if let Some(caps) = version_regex.captures(&raw_response) {
-                    service.version = caps.get(1).map(|m| m.as_str().to_string());
+            let (result, response, closed_early) = self.run_probe(index, stream, soft_match.as_deref()).await;
+            match result {
+                MatchResult::Hard(service) => return (Some(service), response),
+                MatchResult::Soft(service) => soft_match = Some(service),
+                MatchResult::None if closed_early && response.is_empty() && probe.probe.probe_string.is_empty() => {
+                    return (Some(service_named(TCP_WRAPPED)), response);
                 }
+                MatchResult::None => {}
+            }
+            if banner.is_empty() {
+                banner = response;
             }
+        }
+
+        (soft_match.map(|name| service_named(&name)), banner)
+    }
 
-            if let Some(product_regex) = pattern.product_regex {
-                if let Some(caps) = product_regex.captures(&raw_response) {
-                    service.product = caps.get(1).map(|m| m.as_str().to_string());
+    pub fn match_response(&self, protocol: Protocol, port: u16, response: &[u8]) -> Option<Service> {
+        let protocol = match protocol {
+            Protocol::Tcp => "TCP",
+            Protocol::Udp => "UDP",
+        };
+        let mut soft_match = None;
+        for index in self.probe_order(protocol, port) {
+            match self.match_probe(index, response, None) {
+                MatchResult::Hard(service) => return Some(service),
+                MatchResult::Soft(service) => {
+                    soft_match.get_or_insert(service);
                 }
+                MatchResult::None => {}
             }
+        }
+        soft_match.map(|name| service_named(&name))
+    }
+
+    fn probe_order(&self, protocol: &str, port: u16) -> Vec<usize> {
+        let candidates = || self.probes
+            .iter()
+            .enumerate()
+            .filter(move |(_, probe)| probe.probe.protocol == protocol);
+
+        let null = candidates()
+            .filter(|(_, probe)| probe.probe.probe_string.is_empty())
+            .map(|(index, _)| index);
+        let by_port = candidates()
+            .filter(|(_, probe)| !probe.probe.probe_string.is_empty() && probe.probe.ports.contains(&port))
+            .map(|(index, _)| index);
+        let by_rarity = candidates()
+            .filter(|(_, probe)| {
+                !probe.probe.probe_string.is_empty()
+                    && !probe.probe.ports.contains(&port)
+                    && probe.probe.rarity.is_some_and(|rarity| rarity <= VERSION_INTENSITY)
+            })
+            .map(|(index, _)| index);
+        null.chain(by_port).chain(by_rarity).collect()
+    }
 
-            if let Some(os_regex) = pattern.os_regex {
-                if let Some(caps) = os_regex.captures(&raw_response) {
-                    service.os_type = caps.get(1).map(|m| m.as_str().to_string());
+    async fn run_probe(&self, index: usize, mut stream: TcpStream, soft_match: Option<&str>) -> (MatchResult, Vec<u8>, bool) {
+        let probe = &self.probes[index].probe;
+        let started = Instant::now();
+        let deadline = started + Duration::from_millis(probe.total_wait_ms);
+        let mut response = Vec::new();
+
+        if !probe.probe_string.is_empty() && stream.write_all(&probe.probe_string).await.is_err() {
+            return (MatchResult::None, response, false);
+        }
+
+        let mut buffer = [0u8; 4096];
+        while response.len() < MAX_RESPONSE_SIZE {
+            match timeout_at(deadline, stream.read(&mut buffer)).await {
+                Ok(Ok(0)) | Ok(Err(_)) => {
+                    let closed_early = started.elapsed() < Duration::from_millis(probe.tcp_wrapped_ms);
+                    return (self.match_probe(index, &response, soft_match), response, closed_early);
                 }
+                Ok(Ok(n)) => {
+                    response.extend_from_slice(&buffer[..n]);
+                    if let MatchResult::Hard(service) = self.match_probe(index, &response, soft_match) {
+                        return (MatchResult::Hard(service), response, false);
+                    }
+                }
+                Err(_) => break,
             }
+        }
 
-            if let Some(extra_info_regex) = pattern.extra_info_regex {
-                if let Some(caps) = extra_info_regex.captures(&raw_response) {
-                    service.extra_info = caps.get(1).map(|m| m.as_str().to_string());
+        (self.match_probe(index, &response, soft_match), response, false)
+    }
+
+    fn match_probe(&self, index: usize, response: &[u8], soft_match: Option<&str>) -> MatchResult {
+        if response.is_empty() {
+            return MatchResult::None;
+        }
+
+        let probe = &self.probes[index];
+        let mut candidates = vec![index];
+        candidates.extend(&probe.fallback);
+        if probe.probe.protocol == "TCP" {
+            if let Some(null) = self.probes.iter().position(|other| {
+                other.probe.protocol == "TCP" && other.probe.probe_string.is_empty()
+            }) {
+                if !candidates.contains(&null) {
+                    candidates.push(null);
                 }
             }
+        }
 
-            if let Some(cpe_regex) = pattern.cpe_regex {
-                if let Some(caps) = cpe_regex.captures(&raw_response) {
-                    service.cpe = caps.get(1).map(|m| m.as_str().to_string());
+        let mut soft = None;
+        for candidate in candidates {
+            for service_match in &self.probes[candidate].matches {
+                if soft_match.is_some_and(|service| service_match.info.service != service) {
+                    continue;
+                }
+                let Some(captures) = service_match.regex.captures(response) else {
+                    continue;
+                };
+                if service_match.info.soft {
+                    soft.get_or_insert_with(|| service_match.info.service.clone());
+                } else {
+                    return MatchResult::Hard(service_from_match(&service_match.info, &captures));
                 }
             }
+        }
 
-            return Ok((Some(service), raw_response));
+        match soft {
+            Some(service) => MatchResult::Soft(service),
+            None => MatchResult::None,
         }
     }
+}
 
-    Ok((None, raw_response))
-}
\ No newline at end of file
+fn service_from_match(info: &NmapMatch, captures: &Captures) -> Service {
+    let render = |template: Option<&String>| {
+        template.and_then(|template| nmap_template::render(template, captures).ok().flatten())
+    };
+    Service {
+        name: info.service.clone(),
+        version: render(info.version_info.as_ref()),
+        product: render(info.product_info.as_ref()),
+        os_type: render(info.os_info.as_ref()),
+        extra_info: render(info.extra_info.as_ref()),
+        hostname: render(info.hostname_info.as_ref()),
+        device_type: render(info.device_type.as_ref()),
+        cpe: render(info.cpes.first()),
+    }
+}
+
+fn service_named(name: &str) -> Service {
+    Service {
+        name: name.to_string(),
+        version: None,
+        product: None,
+        os_type: None,
+        extra_info: None,
+        hostname: None,
+        device_type: None,
+        cpe: None,
+    }
+}
@@ -0,0 +1,399 @@
+use anyhow::Result;
+use crate::types::{NmapMatch, NmapProbe};
+
+const DEFAULT_TOTAL_WAIT_MS: u64 = 6000;
+const DEFAULT_TCP_WRAPPED_MS: u64 = 3000;
+
+struct Cursor<'a> {
+    line: &'a str,
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(line: &'a str) -> Self {
+        Self { line, pos: 0 }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.line[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn is_empty(&self) -> bool {
+        self.rest().trim().is_empty()
+    }
+
+    fn word(&mut self) -> Option<&'a str> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        if len == 0 {
+            return None;
+        }
+        self.pos += len;
+        Some(&rest[..len])
+    }
+
+    fn eat(&mut self, prefix: &str) -> bool {
+        if self.rest().starts_with(prefix) {
+            self.pos += prefix.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn delimited(&mut self) -> Result<&'a str> {
+        let delimiter = self.rest()
+            .chars()
+            .next()
+            .filter(|c| !c.is_whitespace())
+            .ok_or_else(|| anyhow::anyhow!("expected a delimiter"))?;
+        self.pos += delimiter.len_utf8();
+        let rest = self.rest();
+        let end = rest
+            .find(delimiter)
+            .ok_or_else(|| anyhow::anyhow!("missing closing delimiter '{}'", delimiter))?;
+        self.pos += end + delimiter.len_utf8();
+        Ok(&rest[..end])
+    }
+
+    fn flags(&mut self) -> &'a str {
+        let rest = self.rest();
+        let len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+}
+
+pub fn parse_nmap_probes(content: &str, source: &str) -> Result<Vec<NmapProbe>> {
+    let mut probes: Vec<NmapProbe> = Vec::new();
+
+    for (number, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        parse_line(trimmed, &mut probes)
+            .map_err(|e| anyhow::anyhow!("{}:{}: {}", source, number + 1, e))?;
+    }
+
+    Ok(probes)
+}
+
+fn parse_line(line: &str, probes: &mut Vec<NmapProbe>) -> Result<()> {
+    let mut cursor = Cursor::new(line);
+    let directive = cursor.word().unwrap_or_default();
+
+    if directive == "Probe" {
+        probes.push(parse_probe(&mut cursor)?);
+        return Ok(());
+    }
+    if directive == "Exclude" {
+        return Ok(());
+    }
+
+    let probe = probes
+        .last_mut()
+        .ok_or_else(|| anyhow::anyhow!("'{}' directive before the first Probe", directive))?;
+    let argument = cursor.rest().trim();
+
+    match directive {
+        "match" | "softmatch" => {
+            let nmap_match = parse_match(&mut cursor, directive == "softmatch")?;
+            probe.matches.push(nmap_match);
+        }
+        "ports" => probe.ports = parse_probe_ports(argument)?,
+        "sslports" => probe.ssl_ports = parse_probe_ports(argument)?,
+        "totalwaitms" => probe.total_wait_ms = parse_number(directive, argument)?,
+        "tcpwrappedms" => probe.tcp_wrapped_ms = parse_number(directive, argument)?,
+        "rarity" => {
+            let rarity: u8 = parse_number(directive, argument)?;
+            if !(1..=9).contains(&rarity) {
+                return Err(anyhow::anyhow!("rarity must be between 1 and 9, got {}", rarity));
+            }
+            probe.rarity = Some(rarity);
+        }
+        "fallback" => {
+            probe.fallback = argument
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+        _ => return Err(anyhow::anyhow!("unknown directive '{}'", directive)),
+    }
+    Ok(())
+}
+
+fn parse_probe(cursor: &mut Cursor) -> Result<NmapProbe> {
+    let protocol = cursor.word().ok_or_else(|| anyhow::anyhow!("Probe is missing its protocol"))?;
+    if protocol != "TCP" && protocol != "UDP" {
+        return Err(anyhow::anyhow!("unknown probe protocol '{}'", protocol));
+    }
+    let name = cursor.word().ok_or_else(|| anyhow::anyhow!("Probe is missing its name"))?;
+
+    cursor.skip_whitespace();
+    if !cursor.eat("q") {
+        return Err(anyhow::anyhow!("probe {} is missing its q<delimiter>string<delimiter>", name));
+    }
+    let probe_string = decode_probe_string(cursor.delimited()?)?;
+
+    let mut no_payload = false;
+    let mut source_port = None;
+    while let Some(option) = cursor.word() {
+        match option.split_once('=') {
+            None if option == "no-payload" => no_payload = true,
+            Some(("source", port)) => source_port = Some(parse_number("source", port)?),
+            _ => return Err(anyhow::anyhow!("unknown probe option '{}'", option)),
+        }
+    }
+
+    Ok(NmapProbe {
+        name: name.to_string(),
+        protocol: protocol.to_string(),
+        probe_string,
+        no_payload,
+        source_port,
+        ports: Vec::new(),
+        ssl_ports: Vec::new(),
+        total_wait_ms: DEFAULT_TOTAL_WAIT_MS,
+        tcp_wrapped_ms: DEFAULT_TCP_WRAPPED_MS,
+        rarity: None,
+        fallback: Vec::new(),
+        matches: Vec::new(),
+    })
+}
+
+fn parse_match(cursor: &mut Cursor, soft: bool) -> Result<NmapMatch> {
+    let service = cursor.word().ok_or_else(|| anyhow::anyhow!("match is missing its service name"))?;
+
+    cursor.skip_whitespace();
+    if !cursor.eat("m") {
+        return Err(anyhow::anyhow!("match {} is missing its m<delimiter>pattern<delimiter>", service));
+    }
+    let pattern = cursor.delimited()?;
+
+    let mut nmap_match = NmapMatch {
+        service: service.to_string(),
+        pattern: pattern.to_string(),
+        soft,
+        case_insensitive: false,
+        dot_all: false,
+        version_info: None,
+        product_info: None,
+        os_info: None,
+        extra_info: None,
+        hostname_info: None,
+        device_type: None,
+        cpes: Vec::new(),
+    };
+    for flag in cursor.flags().chars() {
+        match flag {
+            'i' => nmap_match.case_insensitive = true,
+            's' => nmap_match.dot_all = true,
+            _ => return Err(anyhow::anyhow!("unknown regex flag '{}'", flag)),
+        }
+    }
+
+    loop {
+        cursor.skip_whitespace();
+        if cursor.is_empty() {
+            break;
+        }
+
+        if cursor.eat("cpe:") {
+            let cpe = cursor.delimited()?;
+            match cursor.flags() {
+                "" | "a" => nmap_match.cpes.push(format!("cpe:/{}", cpe)),
+                flags => return Err(anyhow::anyhow!("unknown cpe flag '{}'", flags)),
+            }
+            continue;
+        }
+
+        let field = cursor.rest().chars().next().unwrap_or_default();
+        cursor.pos += field.len_utf8();
+        let value = Some(cursor.delimited()?.to_string());
+        match field {
+            'p' => nmap_match.product_info = value,
+            'v' => nmap_match.version_info = value,
+            'i' => nmap_match.extra_info = value,
+            'h' => nmap_match.hostname_info = value,
+            'o' => nmap_match.os_info = value,
+            'd' => nmap_match.device_type = value,
+            _ => return Err(anyhow::anyhow!("unknown version field '{}'", field)),
+        }
+        if !cursor.flags().is_empty() {
+            return Err(anyhow::anyhow!("unexpected text after version field '{}'", field));
+        }
+    }
+
+    Ok(nmap_match)
+}
+
+fn parse_number<T: std::str::FromStr>(directive: &str, value: &str) -> Result<T> {
+    value
+        .parse()
+        .map_err(|_| anyhow::anyhow!("invalid {} value '{}'", directive, value))
+}
+
+fn parse_probe_ports(spec: &str) -> Result<Vec<u16>> {
+    let mut ports = Vec::new();
+    for item in spec.split(',') {
+        let item = item.trim();
+        let (start, end) = item.split_once('-').unwrap_or((item, item));
+        match (start.parse::<u16>(), end.parse::<u16>()) {
+            (Ok(start), Ok(end)) if start <= end => ports.extend(start..=end),
+            _ => return Err(anyhow::anyhow!("invalid port '{}'", item)),
+        }
+    }
+    Ok(ports)
+}
+
+pub fn decode_probe_string(raw: &str) -> Result<Vec<u8>> {
+    let bytes = raw.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] != b'\\' {
+            decoded.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+
+        i += 1;
+        let escape = *bytes
+            .get(i)
+            .ok_or_else(|| anyhow::anyhow!("probe string ends with a backslash"))?;
+        match escape {
+            b'0' => decoded.push(0),
+            b'a' => decoded.push(0x07),
+            b'b' => decoded.push(0x08),
+            b'f' => decoded.push(0x0c),
+            b'n' => decoded.push(b'\n'),
+            b'r' => decoded.push(b'\r'),
+            b't' => decoded.push(b'\t'),
+            b'v' => decoded.push(0x0b),
+            b'x' => {
+                let value = raw
+                    .get(i + 1..i + 3)
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or_else(|| anyhow::anyhow!("invalid \\x escape in probe string"))?;
+                decoded.push(value);
+                i += 2;
+            }
+            b'\\' | b'|' => decoded.push(escape),
+            _ => return Err(anyhow::anyhow!("unknown escape '\\{}' in probe string", escape as char)),
+        }
+        i += 1;
+    }
+
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROBES: &str = r#"
+# comment
+Exclude T:9100-9107
+Probe TCP NULL q||
+totalwaitms 5000
+tcpwrappedms 2000
+match ssh m|^SSH-([\d.]+)-OpenSSH_(\S+)|s p/OpenSSH/ v/$2/ i/protocol $1/ cpe:/a:openbsd:openssh:$2/
+softmatch ftp m=^220 a b=i
+Probe TCP GetRequest q|GET / HTTP/1.0\r\n\r\n| source=1234
+rarity 1
+ports 80,8000-8002
+sslports 443
+fallback NULL, GenericLines
+match http m%^HTTP/1\.[01] \d\d\d% p/generic http/ h/host name/ o/Linux/ d/router/ cpe:|o:linux:linux_kernel|a
+Probe UDP Zero q=\0\x01|\\= no-payload
+"#;
+
+    #[test]
+    fn parses_probe_directives() {
+        let probes = parse_nmap_probes(PROBES, "test").unwrap();
+        assert_eq!(probes.len(), 3);
+
+        let null = &probes[0];
+        assert_eq!((null.name.as_str(), null.protocol.as_str()), ("NULL", "TCP"));
+        assert!(null.probe_string.is_empty());
+        assert_eq!((null.total_wait_ms, null.tcp_wrapped_ms), (5000, 2000));
+        assert_eq!(null.rarity, None);
+
+        let get = &probes[1];
+        assert_eq!(get.probe_string, b"GET / HTTP/1.0\r\n\r\n");
+        assert_eq!(get.source_port, Some(1234));
+        assert_eq!(get.rarity, Some(1));
+        assert_eq!(get.ports, [80, 8000, 8001, 8002]);
+        assert_eq!(get.ssl_ports, [443]);
+        assert_eq!(get.fallback, ["NULL", "GenericLines"]);
+        assert_eq!((get.total_wait_ms, get.tcp_wrapped_ms), (DEFAULT_TOTAL_WAIT_MS, DEFAULT_TCP_WRAPPED_MS));
+
+        let zero = &probes[2];
+        assert_eq!(zero.protocol, "UDP");
+        assert_eq!(zero.probe_string, b"\0\x01|\\");
+        assert!(zero.no_payload);
+    }
+
+    #[test]
+    fn parses_match_delimiters_and_flags() {
+        let probes = parse_nmap_probes(PROBES, "test").unwrap();
+
+        let ssh = &probes[0].matches[0];
+        assert_eq!(ssh.pattern, r"^SSH-([\d.]+)-OpenSSH_(\S+)");
+        assert!(ssh.dot_all && !ssh.case_insensitive && !ssh.soft);
+        assert_eq!(ssh.product_info.as_deref(), Some("OpenSSH"));
+        assert_eq!(ssh.version_info.as_deref(), Some("$2"));
+        assert_eq!(ssh.extra_info.as_deref(), Some("protocol $1"));
+        assert_eq!(ssh.cpes, ["cpe:/a:openbsd:openssh:$2"]);
+
+        let ftp = &probes[0].matches[1];
+        assert_eq!((ftp.service.as_str(), ftp.pattern.as_str()), ("ftp", "^220 a b"));
+        assert!(ftp.soft && ftp.case_insensitive && !ftp.dot_all);
+
+        let http = &probes[1].matches[0];
+        assert_eq!(http.pattern, r"^HTTP/1\.[01] \d\d\d");
+        assert_eq!(http.product_info.as_deref(), Some("generic http"));
+        assert_eq!(http.hostname_info.as_deref(), Some("host name"));
+        assert_eq!(http.os_info.as_deref(), Some("Linux"));
+        assert_eq!(http.device_type.as_deref(), Some("router"));
+        assert_eq!(http.cpes, ["cpe:/o:linux:linux_kernel"]);
+    }
+
+    #[test]
+    fn decodes_probe_string_escapes() {
+        assert_eq!(decode_probe_string(r"a\0\a\b\f\n\r\t\v\xff\x7F\\\|").unwrap(), b"a\0\x07\x08\x0c\n\r\t\x0b\xff\x7f\\|");
+        assert!(decode_probe_string(r"\x4").is_err());
+        assert!(decode_probe_string(r"\xzz").is_err());
+        assert!(decode_probe_string(r"\q").is_err());
+        assert!(decode_probe_string("trailing\\").is_err());
+    }
+
+    #[test]
+    fn reports_errors_with_line_numbers() {
+        let cases = [
+            ("match ssh m|x|", "test:1:"),
+            ("Probe TCP NULL q||\nmatch ssh m|unterminated", "test:2:"),
+            ("Probe TCP NULL q||\n\nmatch ssh m|x|q", "test:3:"),
+            ("Probe SCTP NULL q||", "test:1:"),
+            ("Probe TCP NULL q||\nrarity 10", "test:2:"),
+            ("Probe TCP NULL q||\nports 80-x", "test:2:"),
+            ("Probe TCP NULL q||\nmatch ssh m|x| z/y/", "test:2:"),
+            ("Probe TCP NULL q||\nbogus 1", "test:2:"),
+            ("Probe TCP NULL q|| source=x", "test:1:"),
+        ];
+        for (content, location) in cases {
+            let error = parse_nmap_probes(content, "test").unwrap_err().to_string();
+            assert!(error.starts_with(location), "{:?} gave {:?}", content, error);
+        }
+    }
+}
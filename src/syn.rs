@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket};
+use std::sync::{Arc, Mutex};
+use anyhow::Result;
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+use crate::raw_socket::Responder;
+use crate::types::PortState;
+use crate::timing::Timing;
+
+const TCP_FLAG_FIN: u8 = 0x01;
+const TCP_FLAG_SYN: u8 = 0x02;
+const TCP_FLAG_RST: u8 = 0x04;
+const TCP_FLAG_ACK: u8 = 0x10;
+const TCP_HEADER_LEN: usize = 24;
+const TCP_WINDOW: u16 = 1024;
+const TCP_MSS: u16 = 1460;
+const RECV_BUFFER_SIZE: usize = 4 * 1024 * 1024;
+
+pub struct SynScanner {
+    socket_v4: Arc<Socket>,
+    socket_v6: Option<Arc<Socket>>,
+    source_port: u16,
+    sequence: u32,
+    responder: Responder<(IpAddr, u16), PortState>,
+    source_addrs: Mutex<HashMap<IpAddr, IpAddr>>,
+}
+
+impl SynScanner {
+    pub fn new() -> Result<Self> {
+        if !cfg!(target_os = "linux") {
+            return Err(anyhow::anyhow!("SYN scan is only supported on Linux"));
+        }
+
+        let socket_v4 = Arc::new(open_raw_socket(Domain::IPV4).map_err(|e| {
+            anyhow::anyhow!("Failed to open raw socket (requires root or CAP_NET_RAW): {}", e)
+        })?);
+        let socket_v6 = open_raw_socket(Domain::IPV6).ok().map(Arc::new);
+
+        let source_port = rand::random::<u16>() % 16384 + 40000;
+        let sequence = rand::random::<u32>();
+        let responder = Responder::default();
+
+        responder.spawn_receiver(socket_v4.clone(), move |packet, _| {
+            port_state(parse_ipv4_reply(packet)?, source_port, sequence)
+        })?;
+        if let Some(socket_v6) = &socket_v6 {
+            responder.spawn_receiver(socket_v6.clone(), move |packet, from| {
+                let reply = parse_tcp_reply(from.as_socket()?.ip(), packet)?;
+                port_state(reply, source_port, sequence)
+            })?;
+        }
+
+        Ok(Self {
+            socket_v4,
+            socket_v6,
+            source_port,
+            sequence,
+            responder,
+            source_addrs: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn supports(&self, ip: IpAddr) -> bool {
+        ip.is_ipv4() || self.socket_v6.is_some()
+    }
+
+    pub async fn probe(&self, addr: SocketAddr, timing: &Timing) -> Result<PortState> {
+        let socket = match addr.ip() {
+            IpAddr::V4(_) => &self.socket_v4,
+            IpAddr::V6(_) => self.socket_v6.as_ref()
+                .ok_or_else(|| anyhow::anyhow!("IPv6 raw socket unavailable"))?,
+        };
+        let source = self.source_addr(addr)?;
+        let packet = build_syn_packet(source, addr, self.source_port, self.sequence);
+        let mut dest = addr;
+        dest.set_port(0);
+        let dest = SockAddr::from(dest);
+
+        let state = self.responder
+            .request((addr.ip(), addr.port()), addr.ip(), timing, || socket.send_to(&packet, &dest))
+            .await?;
+        Ok(state.unwrap_or(PortState::Filtered))
+    }
+
+    fn source_addr(&self, addr: SocketAddr) -> Result<IpAddr> {
+        if let Some(source) = self.source_addrs.lock().unwrap().get(&addr.ip()) {
+            return Ok(*source);
+        }
+
+        let bind_addr: SocketAddr = if addr.is_ipv4() {
+            (Ipv4Addr::UNSPECIFIED, 0).into()
+        } else {
+            (Ipv6Addr::UNSPECIFIED, 0).into()
+        };
+        let socket = UdpSocket::bind(bind_addr)?;
+        socket.connect(addr)?;
+        let source = socket.local_addr()?.ip();
+
+        self.source_addrs.lock().unwrap().insert(addr.ip(), source);
+        Ok(source)
+    }
+}
+
+fn open_raw_socket(domain: Domain) -> io::Result<Socket> {
+    let socket = Socket::new(domain, Type::RAW, Some(Protocol::TCP))?;
+    let _ = socket.set_recv_buffer_size(RECV_BUFFER_SIZE);
+    Ok(socket)
+}
+
+fn port_state(
+    (ip, port, dst_port, ack, flags): (IpAddr, u16, u16, u32, u8),
+    source_port: u16,
+    sequence: u32,
+) -> Option<((IpAddr, u16), PortState)> {
+    if dst_port != source_port {
+        return None;
+    }
+    let state = if flags & TCP_FLAG_RST != 0 {
+        PortState::Closed
+    } else if flags & (TCP_FLAG_SYN | TCP_FLAG_ACK) == TCP_FLAG_SYN | TCP_FLAG_ACK
+        && ack == sequence.wrapping_add(1)
+    {
+        PortState::Open
+    } else {
+        return None;
+    };
+    Some(((ip, port), state))
+}
+
+fn parse_ipv4_reply(packet: &[u8]) -> Option<(IpAddr, u16, u16, u32, u8)> {
+    if packet.len() < 20 || packet[0] >> 4 != 4 || packet[9] != 6 {
+        return None;
+    }
+    let header_len = ((packet[0] & 0x0f) as usize) * 4;
+    let source = Ipv4Addr::new(packet[12], packet[13], packet[14], packet[15]);
+    parse_tcp_reply(IpAddr::V4(source), packet.get(header_len..)?)
+}
+
+fn parse_tcp_reply(source: IpAddr, segment: &[u8]) -> Option<(IpAddr, u16, u16, u32, u8)> {
+    if segment.len() < 20 {
+        return None;
+    }
+    let src_port = u16::from_be_bytes([segment[0], segment[1]]);
+    let dst_port = u16::from_be_bytes([segment[2], segment[3]]);
+    let ack = u32::from_be_bytes([segment[8], segment[9], segment[10], segment[11]]);
+    let flags = segment[13] & !TCP_FLAG_FIN;
+    Some((source, src_port, dst_port, ack, flags))
+}
+
+fn build_syn_packet(source: IpAddr, dest: SocketAddr, source_port: u16, sequence: u32) -> Vec<u8> {
+    let mut segment = vec![0u8; TCP_HEADER_LEN];
+    segment[0..2].copy_from_slice(&source_port.to_be_bytes());
+    segment[2..4].copy_from_slice(&dest.port().to_be_bytes());
+    segment[4..8].copy_from_slice(&sequence.to_be_bytes());
+    segment[12] = ((TCP_HEADER_LEN / 4) as u8) << 4;
+    segment[13] = TCP_FLAG_SYN;
+    segment[14..16].copy_from_slice(&TCP_WINDOW.to_be_bytes());
+    segment[20] = 2;
+    segment[21] = 4;
+    segment[22..24].copy_from_slice(&TCP_MSS.to_be_bytes());
+
+    let checksum = tcp_checksum(source, dest.ip(), &segment);
+    segment[16..18].copy_from_slice(&checksum.to_be_bytes());
+    segment
+}
+
+fn tcp_checksum(source: IpAddr, dest: IpAddr, segment: &[u8]) -> u16 {
+    let mut pseudo = Vec::with_capacity(40 + segment.len());
+    match (source, dest) {
+        (IpAddr::V4(src), IpAddr::V4(dst)) => {
+            pseudo.extend_from_slice(&src.octets());
+            pseudo.extend_from_slice(&dst.octets());
+            pseudo.extend_from_slice(&[0, 6]);
+            pseudo.extend_from_slice(&(segment.len() as u16).to_be_bytes());
+        }
+        (src, dst) => {
+            pseudo.extend_from_slice(&to_ipv6(src).octets());
+            pseudo.extend_from_slice(&to_ipv6(dst).octets());
+            pseudo.extend_from_slice(&(segment.len() as u32).to_be_bytes());
+            pseudo.extend_from_slice(&[0, 0, 0, 6]);
+        }
+    }
+    pseudo.extend_from_slice(segment);
+
+    let mut sum: u32 = pseudo
+        .chunks(2)
+        .map(|c| u16::from_be_bytes([c[0], *c.get(1).unwrap_or(&0)]) as u32)
+        .sum();
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+fn to_ipv6(ip: IpAddr) -> Ipv6Addr {
+    match ip {
+        IpAddr::V4(v4) => v4.to_ipv6_mapped(),
+        IpAddr::V6(v6) => v6,
+    }
+}
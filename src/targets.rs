@@ -0,0 +1,585 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+use anyhow::Result;
+use ipnetwork::IpNetwork;
+use crate::dns::resolve_domain;
+use crate::types::{Target, Zone};
+
+const MAX_IPV6_TARGETS: u128 = 1 << 16;
+
+pub type TargetIter = Box<dyn Iterator<Item = Target> + Send>;
+
+#[derive(Debug, Clone)]
+pub enum TargetSpec {
+    Address(IpAddr),
+    Scoped(Ipv6Addr, Zone),
+    Network(IpNetwork),
+    Range(AddressRange),
+    Octets(OctetRange),
+    Hextets(HextetRange),
+    Hostname(String),
+}
+
+impl TargetSpec {
+    pub fn parse(spec: &str, subnet: bool) -> Result<Self> {
+        if let Ok(ip) = IpAddr::from_str(spec) {
+            return Ok(TargetSpec::Address(ip));
+        }
+
+        if let Some((address, zone)) = spec.split_once('%') {
+            let ip = Ipv6Addr::from_str(address)
+                .map_err(|_| anyhow::anyhow!("Zone ids are only valid on IPv6 addresses: {}", spec))?;
+            return Ok(TargetSpec::Scoped(ip, parse_zone(zone)?));
+        }
+
+        if spec.contains('/') {
+            if !subnet {
+                return Err(anyhow::anyhow!("CIDR target {} requires --subnet", spec));
+            }
+            return IpNetwork::from_str(spec)
+                .map(TargetSpec::Network)
+                .map_err(|e| anyhow::anyhow!("Invalid network {}: {}", spec, e));
+        }
+
+        if let Some(range) = AddressRange::parse(spec)? {
+            return Ok(TargetSpec::Range(range));
+        }
+
+        if let Some(octets) = OctetRange::parse(spec) {
+            return Ok(TargetSpec::Octets(octets));
+        }
+
+        if let Some(hextets) = HextetRange::parse(spec) {
+            return Ok(TargetSpec::Hextets(hextets));
+        }
+
+        if is_hostname(spec) {
+            return Ok(TargetSpec::Hostname(spec.to_string()));
+        }
+
+        Err(anyhow::anyhow!("Invalid target: {}", spec))
+    }
+
+    pub fn parse_scannable(spec: &str, subnet: bool) -> Result<Self> {
+        let target_spec = Self::parse(spec, subnet)?;
+        let ipv6 = match &target_spec {
+            TargetSpec::Network(network) => network.is_ipv6(),
+            TargetSpec::Range(range) => range.start.is_ipv6(),
+            TargetSpec::Hextets(_) => true,
+            _ => false,
+        };
+        if ipv6 && target_spec.address_count().is_none_or(|count| count > MAX_IPV6_TARGETS) {
+            return Err(anyhow::anyhow!(
+                "IPv6 target {} covers more than {} addresses; use a /112 or smaller network, \
+                 list hosts explicitly or use a sparse pattern such as 2001:db8::1-ff or 2001:db8:0-f::1",
+                spec,
+                MAX_IPV6_TARGETS,
+            ));
+        }
+        Ok(target_spec)
+    }
+
+    pub fn address_count(&self) -> Option<u128> {
+        match self {
+            TargetSpec::Address(_) | TargetSpec::Scoped(..) => Some(1),
+            TargetSpec::Network(IpNetwork::V4(network)) => Some(1u128 << (32 - network.prefix())),
+            TargetSpec::Network(IpNetwork::V6(network)) => 1u128.checked_shl(128 - network.prefix() as u32),
+            TargetSpec::Range(range) => range.address_count(),
+            TargetSpec::Octets(octets) => Some(octets.address_count()),
+            TargetSpec::Hextets(hextets) => Some(hextets.address_count()),
+            TargetSpec::Hostname(_) => None,
+        }
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match self {
+            TargetSpec::Address(address) => *address == ip,
+            TargetSpec::Scoped(address, _) => IpAddr::V6(*address) == ip,
+            TargetSpec::Network(network) => network.contains(ip),
+            TargetSpec::Range(range) => range.contains(ip),
+            TargetSpec::Octets(octets) => octets.contains(ip),
+            TargetSpec::Hextets(hextets) => hextets.contains(ip),
+            TargetSpec::Hostname(_) => false,
+        }
+    }
+
+    pub async fn resolve(self) -> Result<TargetIter> {
+        Ok(match self {
+            TargetSpec::Address(ip) => Box::new(std::iter::once(Target::from(ip))),
+            TargetSpec::Scoped(ip, zone) => Box::new(std::iter::once(Target {
+                zone: Some(zone),
+                ..Target::from(IpAddr::V6(ip))
+            })),
+            TargetSpec::Network(network) => Box::new(network.iter().map(Target::from)),
+            TargetSpec::Range(range) => Box::new(range.iter().map(Target::from)),
+            TargetSpec::Octets(octets) => Box::new(octets.iter().map(Target::from)),
+            TargetSpec::Hextets(hextets) => Box::new(hextets.iter().map(Target::from)),
+            TargetSpec::Hostname(hostname) => {
+                let ips = resolve_domain(&hostname).await?;
+                Box::new(ips.into_iter().map(move |ip| Target {
+                    hostname: Some(hostname.clone()),
+                    ..Target::from(ip)
+                }))
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AddressRange {
+    start: IpAddr,
+    end: IpAddr,
+}
+
+impl AddressRange {
+    pub fn new(start: IpAddr, end: IpAddr) -> Result<Self> {
+        if start.is_ipv4() != end.is_ipv4() {
+            return Err(anyhow::anyhow!("Address range {}-{} mixes IPv4 and IPv6", start, end));
+        }
+        if to_u128(start) > to_u128(end) {
+            return Err(anyhow::anyhow!("Address range start {} is after end {}", start, end));
+        }
+        Ok(Self { start, end })
+    }
+
+    pub fn parse(spec: &str) -> Result<Option<Self>> {
+        let Some((start, end)) = spec.split_once('-') else {
+            return Ok(None);
+        };
+        let Ok(start) = IpAddr::from_str(start) else {
+            return Ok(None);
+        };
+
+        if let Ok(end) = IpAddr::from_str(end) {
+            return Self::new(start, end).map(Some);
+        }
+
+        if let IpAddr::V6(start_v6) = start {
+            if !end.is_empty() && end.len() <= 4 {
+                if let Ok(last) = u16::from_str_radix(end, 16) {
+                    let end = (u128::from(start_v6) & !0xffff) | last as u128;
+                    return Self::new(start, IpAddr::V6(Ipv6Addr::from(end))).map(Some);
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    pub fn address_count(&self) -> Option<u128> {
+        (to_u128(self.end) - to_u128(self.start)).checked_add(1)
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        ip.is_ipv4() == self.start.is_ipv4()
+            && to_u128(self.start) <= to_u128(ip)
+            && to_u128(ip) <= to_u128(self.end)
+    }
+
+    pub fn iter(&self) -> AddressRangeIter {
+        AddressRangeIter {
+            next: to_u128(self.start),
+            end: to_u128(self.end),
+            ipv4: self.start.is_ipv4(),
+            done: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AddressRangeIter {
+    next: u128,
+    end: u128,
+    ipv4: bool,
+    done: bool,
+}
+
+impl Iterator for AddressRangeIter {
+    type Item = IpAddr;
+
+    fn next(&mut self) -> Option<IpAddr> {
+        if self.done {
+            return None;
+        }
+        let current = self.next;
+        if current == self.end {
+            self.done = true;
+        } else {
+            self.next += 1;
+        }
+        Some(from_u128(current, self.ipv4))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OctetRange {
+    octets: [(u8, u8); 4],
+}
+
+impl OctetRange {
+    pub fn parse(spec: &str) -> Option<Self> {
+        let parts: Vec<&str> = spec.split('.').collect();
+        if parts.len() != 4 || parts.iter().all(|p| p.parse::<u8>().is_ok()) {
+            return None;
+        }
+
+        let mut octets = [(0u8, 0u8); 4];
+        for (octet, part) in octets.iter_mut().zip(parts) {
+            *octet = match part {
+                "*" => (0, 255),
+                _ => match part.split_once('-') {
+                    Some((lo, hi)) => {
+                        let lo = if lo.is_empty() { 0 } else { lo.parse().ok()? };
+                        let hi = if hi.is_empty() { 255 } else { hi.parse().ok()? };
+                        (lo, hi)
+                    }
+                    None => {
+                        let value = part.parse().ok()?;
+                        (value, value)
+                    }
+                },
+            };
+            if octet.0 > octet.1 {
+                return None;
+            }
+        }
+
+        Some(Self { octets })
+    }
+
+    pub fn address_count(&self) -> u128 {
+        self.octets.iter().map(|&(lo, hi)| (hi - lo) as u128 + 1).product()
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match ip {
+            IpAddr::V4(ip) => ip
+                .octets()
+                .iter()
+                .zip(self.octets.iter())
+                .all(|(&value, &(lo, hi))| lo <= value && value <= hi),
+            IpAddr::V6(_) => false,
+        }
+    }
+
+    pub fn iter(&self) -> OctetRangeIter {
+        OctetRangeIter {
+            octets: self.octets,
+            current: self.octets.map(|(lo, _)| lo),
+            done: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OctetRangeIter {
+    octets: [(u8, u8); 4],
+    current: [u8; 4],
+    done: bool,
+}
+
+impl Iterator for OctetRangeIter {
+    type Item = IpAddr;
+
+    fn next(&mut self) -> Option<IpAddr> {
+        if self.done {
+            return None;
+        }
+        let address = IpAddr::V4(Ipv4Addr::from(self.current));
+
+        self.done = true;
+        for i in (0..4).rev() {
+            if self.current[i] < self.octets[i].1 {
+                self.current[i] += 1;
+                self.done = false;
+                break;
+            }
+            self.current[i] = self.octets[i].0;
+        }
+
+        Some(address)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HextetRange {
+    hextets: [(u16, u16); 8],
+}
+
+impl HextetRange {
+    pub fn parse(spec: &str) -> Option<Self> {
+        if !spec.contains(':') || !spec.contains(['-', '*']) {
+            return None;
+        }
+
+        let (head, tail) = match spec.split_once("::") {
+            Some((head, tail)) => (head, Some(tail)),
+            None => (spec, None),
+        };
+        let split = |part: &str| -> Vec<String> {
+            if part.is_empty() {
+                Vec::new()
+            } else {
+                part.split(':').map(str::to_string).collect()
+            }
+        };
+        let head = split(head);
+        let tail = tail.map(split);
+
+        let parts = match tail {
+            Some(tail) if head.len() + tail.len() < 8 => {
+                let zeros = vec!["0".to_string(); 8 - head.len() - tail.len()];
+                [head, zeros, tail].concat()
+            }
+            Some(_) => return None,
+            None if head.len() == 8 => head,
+            None => return None,
+        };
+
+        let mut hextets = [(0u16, 0u16); 8];
+        for (hextet, part) in hextets.iter_mut().zip(&parts) {
+            let parse = |value: &str| u16::from_str_radix(value, 16).ok().filter(|_| value.len() <= 4);
+            *hextet = match part.as_str() {
+                "*" => (0, 0xffff),
+                part => match part.split_once('-') {
+                    Some((lo, hi)) => {
+                        let lo = if lo.is_empty() { 0 } else { parse(lo)? };
+                        let hi = if hi.is_empty() { 0xffff } else { parse(hi)? };
+                        (lo, hi)
+                    }
+                    None => {
+                        let value = parse(part)?;
+                        (value, value)
+                    }
+                },
+            };
+            if hextet.0 > hextet.1 {
+                return None;
+            }
+        }
+
+        Some(Self { hextets })
+    }
+
+    pub fn address_count(&self) -> u128 {
+        self.hextets
+            .iter()
+            .map(|&(lo, hi)| (hi - lo) as u128 + 1)
+            .fold(1u128, |count, size| count.saturating_mul(size))
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match ip {
+            IpAddr::V6(ip) => ip
+                .segments()
+                .iter()
+                .zip(self.hextets.iter())
+                .all(|(&value, &(lo, hi))| lo <= value && value <= hi),
+            IpAddr::V4(_) => false,
+        }
+    }
+
+    pub fn iter(&self) -> HextetRangeIter {
+        HextetRangeIter {
+            hextets: self.hextets,
+            current: self.hextets.map(|(lo, _)| lo),
+            done: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HextetRangeIter {
+    hextets: [(u16, u16); 8],
+    current: [u16; 8],
+    done: bool,
+}
+
+impl Iterator for HextetRangeIter {
+    type Item = IpAddr;
+
+    fn next(&mut self) -> Option<IpAddr> {
+        if self.done {
+            return None;
+        }
+        let address = IpAddr::V6(Ipv6Addr::from(self.current));
+
+        self.done = true;
+        for i in (0..8).rev() {
+            if self.current[i] < self.hextets[i].1 {
+                self.current[i] += 1;
+                self.done = false;
+                break;
+            }
+            self.current[i] = self.hextets[i].0;
+        }
+
+        Some(address)
+    }
+}
+
+pub fn parse_zone(zone: &str) -> Result<Zone> {
+    if zone.is_empty() {
+        return Err(anyhow::anyhow!("Empty IPv6 zone id"));
+    }
+    let index = match zone.parse::<u32>() {
+        Ok(index) => index,
+        Err(_) => interface_index(zone)?,
+    };
+    Ok(Zone {
+        name: zone.to_string(),
+        index,
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn interface_index(name: &str) -> Result<u32> {
+    let c_name = std::ffi::CString::new(name)
+        .map_err(|_| anyhow::anyhow!("Invalid interface name: {}", name))?;
+    // SAFETY: `c_name` is a valid NUL-terminated string for the duration of the call.
+    match unsafe { libc::if_nametoindex(c_name.as_ptr()) } {
+        0 => Err(anyhow::anyhow!("Unknown network interface: {}", name)),
+        index => Ok(index),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn interface_index(name: &str) -> Result<u32> {
+    Err(anyhow::anyhow!("Use a numeric interface index as the zone id instead of {}", name))
+}
+
+pub fn is_hostname(target: &str) -> bool {
+    !target.is_empty()
+        && target.len() <= 253
+        && target.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+}
+
+fn to_u128(ip: IpAddr) -> u128 {
+    match ip {
+        IpAddr::V4(ip) => u32::from(ip) as u128,
+        IpAddr::V6(ip) => u128::from(ip),
+    }
+}
+
+fn from_u128(value: u128, ipv4: bool) -> IpAddr {
+    if ipv4 {
+        IpAddr::V4(Ipv4Addr::from(value as u32))
+    } else {
+        IpAddr::V6(Ipv6Addr::from(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ips(spec: &str) -> Vec<String> {
+        let iter: Box<dyn Iterator<Item = IpAddr>> = match TargetSpec::parse(spec, true).unwrap() {
+            TargetSpec::Range(range) => Box::new(range.iter()),
+            TargetSpec::Octets(octets) => Box::new(octets.iter()),
+            TargetSpec::Hextets(hextets) => Box::new(hextets.iter()),
+            other => panic!("{} parsed as {:?}", spec, other),
+        };
+        iter.map(|ip| ip.to_string()).collect()
+    }
+
+    #[test]
+    fn expands_octet_ranges() {
+        assert_eq!(ips("10.0.1-2.5-6"), ["10.0.1.5", "10.0.1.6", "10.0.2.5", "10.0.2.6"]);
+        assert_eq!(ips("10.0.0.254-"), ["10.0.0.254", "10.0.0.255"]);
+        assert_eq!(ips("10.0.0.-1"), ["10.0.0.0", "10.0.0.1"]);
+        assert_eq!(ips("10.0.0.*").len(), 256);
+    }
+
+    #[test]
+    fn octet_range_counts_and_contains() {
+        let TargetSpec::Octets(octets) = TargetSpec::parse("10.0-1.*.1-254", false).unwrap() else {
+            panic!("expected an octet range");
+        };
+        assert_eq!(octets.address_count(), 2 * 256 * 254);
+        assert!(octets.contains("10.1.7.254".parse().unwrap()));
+        assert!(!octets.contains("10.2.7.1".parse().unwrap()));
+        assert!(!octets.contains("10.0.7.255".parse().unwrap()));
+    }
+
+    #[test]
+    fn rejects_invalid_octet_ranges() {
+        for spec in ["10.0.0.5-1", "10.0.0.256", "10.0.0-1", "10.0.0.1-2.3", "10.0.0.1"] {
+            assert!(OctetRange::parse(spec).is_none(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn expands_address_ranges() {
+        assert_eq!(ips("192.168.1.254-192.168.2.1"), ["192.168.1.254", "192.168.1.255", "192.168.2.0", "192.168.2.1"]);
+        assert_eq!(ips("2001:db8::fffe-2001:db8::1:0"), ["2001:db8::fffe", "2001:db8::ffff", "2001:db8::1:0"]);
+        assert_eq!(ips("2001:db8::1-3"), ["2001:db8::1", "2001:db8::2", "2001:db8::3"]);
+    }
+
+    #[test]
+    fn rejects_invalid_address_ranges() {
+        assert!(TargetSpec::parse("10.0.0.9-10.0.0.1", false).is_err());
+        assert!(TargetSpec::parse("10.0.0.1-2001:db8::1", false).is_err());
+        assert!(TargetSpec::parse("10.0.0.0/24", false).is_err());
+        assert!(matches!(TargetSpec::parse("10.0.0.0/24", true).unwrap(), TargetSpec::Network(_)));
+    }
+
+    #[test]
+    fn counts_full_ipv6_range() {
+        let range = AddressRange::new("::".parse().unwrap(), "ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff".parse().unwrap()).unwrap();
+        assert_eq!(range.address_count(), None);
+        assert_eq!(ips("10.255.255.255-11.0.0.0").len(), 2);
+    }
+
+    #[test]
+    fn expands_hextet_ranges() {
+        assert_eq!(ips("2001:db8:0-1::5-6"), ["2001:db8::5", "2001:db8::6", "2001:db8:1::5", "2001:db8:1::6"]);
+        assert_eq!(ips("2001:db8::fffe-:1"), ["2001:db8::fffe:1", "2001:db8::ffff:1"]);
+        assert_eq!(ips("2001:db8:0:0:0:0:0:*").len(), 65536);
+
+        let TargetSpec::Hextets(hextets) = TargetSpec::parse("2001:db8:*::1", false).unwrap() else {
+            panic!("expected a hextet range");
+        };
+        assert_eq!(hextets.address_count(), 65536);
+        assert!(hextets.contains("2001:db8:abcd::1".parse().unwrap()));
+        assert!(!hextets.contains("2001:db8:abcd::2".parse().unwrap()));
+        assert!(!hextets.contains("10.0.0.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn rejects_invalid_hextet_ranges() {
+        for spec in ["2001:db8::5-1:1", "2001:db8::10000-:1", "1:2:3:4:5:6:7:8:9-a", "1:2::3::4-5", "2001:db8::1"] {
+            assert!(HextetRange::parse(spec).is_none(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn limits_ipv6_targets_to_65536_addresses() {
+        for spec in ["2001:db8::/112", "2001:db8::*:1", "2001:db8::-2001:db8::ffff", "2001:db8::0-ffff"] {
+            assert!(TargetSpec::parse_scannable(spec, true).is_ok(), "{}", spec);
+        }
+        for spec in ["2001:db8::/111", "2001:db8::/64", "2001:db8::0-1:*", "2001:db8::-2001:db8::1:0", "::-ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff"] {
+            assert!(TargetSpec::parse_scannable(spec, true).is_err(), "{}", spec);
+        }
+        assert!(TargetSpec::parse_scannable("10.0.0.0/8", true).is_ok());
+    }
+
+    #[test]
+    fn parses_zone_ids() {
+        let TargetSpec::Scoped(ip, zone) = TargetSpec::parse("fe80::1%3", false).unwrap() else {
+            panic!("expected a scoped address");
+        };
+        assert_eq!(ip, "fe80::1".parse::<Ipv6Addr>().unwrap());
+        assert_eq!((zone.name.as_str(), zone.index), ("3", 3));
+
+        assert!(TargetSpec::parse("fe80::1%", false).is_err());
+        assert!(TargetSpec::parse("10.0.0.1%3", false).is_err());
+        assert!(TargetSpec::parse("fe80::1%no-such-interface0", false).is_err());
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::Duration;
+use anyhow::Result;
+use crate::rate_limit::RateLimiter;
+use crate::congestion::CongestionController;
+
+const MAX_TRACKED_HOSTS: usize = 65536;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimingConfig {
+    pub initial_rtt_timeout: Duration,
+    pub min_rtt_timeout: Duration,
+    pub max_rtt_timeout: Duration,
+    pub max_retries: u32,
+    pub scan_delay: Duration,
+    pub max_parallelism: Option<usize>,
+}
+
+impl TimingConfig {
+    pub fn template(level: u8) -> Result<Self> {
+        let ms = Duration::from_millis;
+        let (initial, min, max, retries, delay, parallelism) = match level {
+            0 => (ms(300_000), ms(100), ms(10_000), 10, ms(300_000), Some(1)),
+            1 => (ms(15_000), ms(100), ms(10_000), 10, ms(15_000), Some(1)),
+            2 => (ms(1_000), ms(100), ms(10_000), 10, ms(400), Some(1)),
+            3 => (ms(1_000), ms(100), ms(10_000), 10, ms(0), None),
+            4 => (ms(500), ms(100), ms(1_250), 6, ms(0), None),
+            5 => (ms(250), ms(50), ms(300), 2, ms(0), None),
+            _ => return Err(anyhow::anyhow!("Timing template must be between 0 and 5")),
+        };
+
+        Ok(Self {
+            initial_rtt_timeout: initial,
+            min_rtt_timeout: min,
+            max_rtt_timeout: max,
+            max_retries: retries,
+            scan_delay: delay,
+            max_parallelism: parallelism,
+        })
+    }
+
+    pub fn with_fixed_timeout(mut self, timeout: Duration) -> Self {
+        self.initial_rtt_timeout = timeout;
+        self.max_rtt_timeout = timeout;
+        self.min_rtt_timeout = timeout;
+        self
+    }
+}
+
+impl Default for TimingConfig {
+    fn default() -> Self {
+        Self::template(3).unwrap()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct RttEstimate {
+    srtt: Option<f64>,
+    rttvar: f64,
+}
+
+impl RttEstimate {
+    fn update(&mut self, sample: f64) {
+        match self.srtt {
+            None => {
+                self.srtt = Some(sample);
+                self.rttvar = sample / 2.0;
+            }
+            Some(srtt) => {
+                self.rttvar = 0.75 * self.rttvar + 0.25 * (srtt - sample).abs();
+                self.srtt = Some(0.875 * srtt + 0.125 * sample);
+            }
+        }
+    }
+
+    fn timeout(&self, config: &TimingConfig) -> Option<Duration> {
+        let srtt = self.srtt?;
+        let rto = Duration::from_secs_f64(srtt + 4.0 * self.rttvar);
+        Some(rto.clamp(config.min_rtt_timeout, config.max_rtt_timeout))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct HostTiming {
+    rtt: RttEstimate,
+    max_answered_try: u32,
+}
+
+#[derive(Debug)]
+pub struct Timing {
+    config: TimingConfig,
+    rate_limiter: Option<RateLimiter>,
+    congestion: CongestionController,
+    hosts: Mutex<HashMap<IpAddr, HostTiming>>,
+    global: Mutex<RttEstimate>,
+}
+
+impl Timing {
+    pub fn new(config: TimingConfig, max_rate: Option<f64>, min_parallelism: usize, max_parallelism: usize) -> Self {
+        Self {
+            config,
+            rate_limiter: max_rate.map(RateLimiter::new),
+            congestion: CongestionController::new(min_parallelism, max_parallelism),
+            hosts: Mutex::new(HashMap::new()),
+            global: Mutex::new(RttEstimate::default()),
+        }
+    }
+
+    pub fn config(&self) -> &TimingConfig {
+        &self.config
+    }
+
+    pub fn congestion(&self) -> &CongestionController {
+        &self.congestion
+    }
+
+    pub async fn pace(&self) {
+        if !self.config.scan_delay.is_zero() {
+            tokio::time::sleep(self.config.scan_delay).await;
+        }
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire().await;
+        }
+    }
+
+    pub fn timeout_for(&self, ip: IpAddr) -> Duration {
+        let host = self.hosts.lock().unwrap().get(&ip).copied();
+        host.and_then(|host| host.rtt.timeout(&self.config))
+            .or_else(|| self.global.lock().unwrap().timeout(&self.config))
+            .map(|timeout| timeout.max(self.config.min_rtt_timeout))
+            .unwrap_or(self.config.initial_rtt_timeout)
+    }
+
+    pub fn retries_for(&self, ip: IpAddr) -> u32 {
+        let max_answered_try = self.hosts.lock().unwrap()
+            .get(&ip)
+            .map_or(0, |host| host.max_answered_try);
+        self.config.max_retries.min(max_answered_try + 1)
+    }
+
+    pub fn record(&self, ip: IpAddr, rtt: Duration) {
+        let sample = rtt.as_secs_f64();
+        self.update_host(ip, |host| host.rtt.update(sample));
+        self.global.lock().unwrap().update(sample);
+        self.congestion.on_success();
+    }
+
+    pub fn record_retransmitted(&self, ip: IpAddr, attempt: u32) {
+        self.update_host(ip, |host| host.max_answered_try = host.max_answered_try.max(attempt));
+        self.congestion.on_loss(self.timeout_for(ip));
+    }
+
+    pub fn record_unanswered(&self, ip: IpAddr) {
+        self.congestion.on_timeout(self.timeout_for(ip));
+    }
+
+    fn update_host(&self, ip: IpAddr, update: impl FnOnce(&mut HostTiming)) {
+        let mut hosts = self.hosts.lock().unwrap();
+        if hosts.len() >= MAX_TRACKED_HOSTS && !hosts.contains_key(&ip) {
+            hosts.clear();
+        }
+        update(hosts.entry(ip).or_default());
+    }
+}
@@ -1,5 +1,61 @@
+use std::fmt;
 use serde::Serialize;
-use regex::Regex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    Tcp,
+    Udp,
+}
+
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Protocol::Tcp => write!(f, "tcp"),
+            Protocol::Udp => write!(f, "udp"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ScanType {
+    Connect,
+    Syn,
+    Udp,
+}
+
+impl ScanType {
+    pub fn protocol(&self) -> Protocol {
+        match self {
+            ScanType::Connect | ScanType::Syn => Protocol::Tcp,
+            ScanType::Udp => Protocol::Udp,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum PortState {
+    #[serde(rename = "open")]
+    Open,
+    #[serde(rename = "closed")]
+    Closed,
+    #[serde(rename = "filtered")]
+    Filtered,
+    #[serde(rename = "open|filtered")]
+    OpenFiltered,
+}
+
+impl fmt::Display for PortState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PortState::Open => write!(f, "open"),
+            PortState::Closed => write!(f, "closed"),
+            PortState::Filtered => write!(f, "filtered"),
+            PortState::OpenFiltered => write!(f, "open|filtered"),
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize)]
 pub struct Service {
@@ -8,28 +64,95 @@ pub struct Service {
     pub product: Option<String>,
     pub os_type: Option<String>,
     pub extra_info: Option<String>,
+    pub hostname: Option<String>,
+    pub device_type: Option<String>,
     pub cpe: Option<String>,
 }
 
-#[derive(Debug, Clone)]
-pub struct ServicePattern {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MacAddress(pub [u8; 6]);
+
+impl fmt::Display for MacAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(f, "{:02X}:{:02X}:{:02X}:{:02X}:{:02X}:{:02X}", a, b, c, d, e, g)
+    }
+}
+
+impl Serialize for MacAddress {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl std::str::FromStr for MacAddress {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let octets: Vec<u8> = s
+            .split([':', '-'])
+            .map(|octet| u8::from_str_radix(octet, 16))
+            .collect::<Result<_, _>>()
+            .map_err(|_| anyhow::anyhow!("Invalid MAC address: {}", s))?;
+        let octets: [u8; 6] = octets
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Invalid MAC address: {}", s))?;
+        Ok(Self(octets))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Zone {
     pub name: String,
-    pub regex: Regex,
-    pub probe: String,
-    pub version_regex: Option<Regex>,
-    pub product_regex: Option<Regex>,
-    pub os_regex: Option<Regex>,
-    pub extra_info_regex: Option<Regex>,
-    pub cpe_regex: Option<Regex>,
-    pub vulnerability_patterns: Vec<Regex>,
-    pub total_wait_ms: u64,
-    pub tcp_wrapped_ms: u64,
+    pub index: u32,
 }
 
-#[derive(Debug)]
+impl fmt::Display for Zone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl Serialize for Zone {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    pub ip: std::net::IpAddr,
+    pub zone: Option<Zone>,
+    pub hostname: Option<String>,
+    pub mac: Option<MacAddress>,
+    pub vendor: Option<String>,
+}
+
+impl Target {
+    pub fn socket_addr(&self, port: u16) -> std::net::SocketAddr {
+        match (self.ip, &self.zone) {
+            (std::net::IpAddr::V6(ip), Some(zone)) => std::net::SocketAddrV6::new(ip, port, 0, zone.index).into(),
+            (ip, _) => std::net::SocketAddr::new(ip, port),
+        }
+    }
+}
+
+impl From<std::net::IpAddr> for Target {
+    fn from(ip: std::net::IpAddr) -> Self {
+        Self { ip, zone: None, hostname: None, mac: None, vendor: None }
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct ScanResult {
     pub ip: std::net::IpAddr,
+    pub zone: Option<Zone>,
+    pub hostname: Option<String>,
+    pub mac: Option<MacAddress>,
+    pub vendor: Option<String>,
     pub port: u16,
+    pub protocol: Protocol,
+    pub state: PortState,
     pub service: Option<Service>,
     pub raw_response: String,
 }
@@ -39,6 +162,7 @@ pub struct NmapService {
     pub name: String,
     pub port: u16,
     pub protocol: String,
+    pub frequency: f64,
     pub description: Option<String>,
 }
 
@@ -46,9 +170,15 @@ pub struct NmapService {
 pub struct NmapProbe {
     pub name: String,
     pub protocol: String,
-    pub probe_string: String,
+    pub probe_string: Vec<u8>,
+    pub no_payload: bool,
+    pub source_port: Option<u16>,
+    pub ports: Vec<u16>,
+    pub ssl_ports: Vec<u16>,
     pub total_wait_ms: u64,
     pub tcp_wrapped_ms: u64,
+    pub rarity: Option<u8>,
+    pub fallback: Vec<String>,
     pub matches: Vec<NmapMatch>,
 }
 
@@ -56,9 +186,14 @@ pub struct NmapProbe {
 pub struct NmapMatch {
     pub service: String,
     pub pattern: String,
+    pub soft: bool,
+    pub case_insensitive: bool,
+    pub dot_all: bool,
     pub version_info: Option<String>,
     pub product_info: Option<String>,
     pub os_info: Option<String>,
     pub extra_info: Option<String>,
-    pub cpe: Option<String>,
+    pub hostname_info: Option<String>,
+    pub device_type: Option<String>,
+    pub cpes: Vec<String>,
 }
\ No newline at end of file
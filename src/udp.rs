@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::time::Instant;
+use tokio::net::UdpSocket;
+use tokio::time::timeout;
+use anyhow::Result;
+use crate::types::{NmapProbe, PortState};
+use crate::patterns::load_nmap_probes;
+use crate::timing::Timing;
+
+const MAX_DATAGRAM_SIZE: usize = 4096;
+
+#[derive(Debug, Clone, Default)]
+pub struct UdpPayloads {
+    by_port: HashMap<u16, Vec<u8>>,
+}
+
+impl UdpPayloads {
+    pub fn load(file_path: &str) -> Result<Self> {
+        Ok(Self::from_probes(&load_nmap_probes(file_path)?))
+    }
+
+    pub fn from_probes(probes: &[NmapProbe]) -> Self {
+        let mut by_port = HashMap::new();
+
+        for probe in probes {
+            if probe.protocol != "UDP" || probe.no_payload || probe.probe_string.is_empty() {
+                continue;
+            }
+            for &port in &probe.ports {
+                by_port.entry(port).or_insert_with(|| probe.probe_string.clone());
+            }
+        }
+
+        Self { by_port }
+    }
+
+    pub fn get(&self, port: u16) -> &[u8] {
+        self.by_port.get(&port).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_port.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_port.is_empty()
+    }
+}
+
+pub async fn probe_udp(
+    addr: SocketAddr,
+    payload: &[u8],
+    timing: &Timing,
+) -> Result<(PortState, Vec<u8>)> {
+    let bind_addr: SocketAddr = if addr.is_ipv4() {
+        "0.0.0.0:0".parse()?
+    } else {
+        "[::]:0".parse()?
+    };
+    let socket = UdpSocket::bind(bind_addr).await?;
+    socket.connect(addr).await?;
+
+    let mut buffer = [0u8; MAX_DATAGRAM_SIZE];
+    for attempt in 0..=timing.retries_for(addr.ip()) {
+        timing.pace().await;
+        let started = Instant::now();
+        if let Err(e) = socket.send(payload).await {
+            return Ok((classify_udp_error(addr, &e)?, Vec::new()));
+        }
+
+        match timeout(timing.timeout_for(addr.ip()), socket.recv(&mut buffer)).await {
+            Ok(result) => {
+                if attempt == 0 {
+                    timing.record(addr.ip(), started.elapsed());
+                } else {
+                    timing.record_retransmitted(addr.ip(), attempt);
+                }
+                return match result {
+                    Ok(n) => Ok((PortState::Open, buffer[..n].to_vec())),
+                    Err(e) => Ok((classify_udp_error(addr, &e)?, Vec::new())),
+                };
+            }
+            Err(_) => continue,
+        }
+    }
+
+    timing.record_unanswered(addr.ip());
+    Ok((PortState::OpenFiltered, Vec::new()))
+}
+
+fn classify_udp_error(addr: SocketAddr, error: &io::Error) -> Result<PortState> {
+    match error.kind() {
+        io::ErrorKind::ConnectionRefused => Ok(PortState::Closed),
+        io::ErrorKind::HostUnreachable | io::ErrorKind::NetworkUnreachable => Ok(PortState::Filtered),
+        _ => Err(anyhow::anyhow!("Failed to probe {}/udp: {}", addr, error)),
+    }
+}
@@ -1,8 +1,13 @@
-use crate::types::ScanResult;
+use std::net::IpAddr;
+use crate::types::{MacAddress, ScanResult, Target, Zone};
 
 pub fn format_scan_result(result: &ScanResult) -> String {
-    let mut output = format!("[+] {}:{} is open", result.ip, result.port);
-    
+    let endpoint = format_endpoint(result.ip, result.zone.as_ref(), result.hostname.as_deref(), result.port);
+    let mut output = format!("[+] {}/{} is {}", endpoint, result.protocol, result.state);
+    if let Some(mac) = &result.mac {
+        output.push_str(&format!("\n    MAC Address: {}", format_mac(mac, result.vendor.as_deref())));
+    }
+
     if let Some(service) = &result.service {
         output.push_str(&format!("\n    Service: {}", service.name));
         if let Some(version) = &service.version {
@@ -15,13 +20,54 @@ pub fn format_scan_result(result: &ScanResult) -> String {
             output.push_str(&format!("\n    OS: {}", os_type));
         }
         if let Some(extra_info) = &service.extra_info {
-            if !extra_info.is_empty() && extra_info.len() < 100 {
-                output.push_str(&format!("\n    Extra: {}", extra_info));
-            }
+            output.push_str(&format!("\n    Extra Info: {}", extra_info));
+        }
+        if let Some(hostname) = &service.hostname {
+            output.push_str(&format!("\n    Hostname: {}", hostname));
+        }
+        if let Some(device_type) = &service.device_type {
+            output.push_str(&format!("\n    Device Type: {}", device_type));
         }
-    } else {
-        output.push_str("\n    Service: unknown (please report fingerprint on issues)");
     }
-    
+
+    output
+}
+
+pub fn format_live_host(target: &Target) -> String {
+    let mut output = format!(
+        "[+] {} is up",
+        format_host(target.ip, target.zone.as_ref(), target.hostname.as_deref()),
+    );
+    if let Some(mac) = &target.mac {
+        output.push_str(&format!("\n    MAC Address: {}", format_mac(mac, target.vendor.as_deref())));
+    }
     output
-} 
\ No newline at end of file
+}
+
+pub fn format_mac(mac: &MacAddress, vendor: Option<&str>) -> String {
+    match vendor {
+        Some(vendor) => format!("{} ({})", mac, vendor),
+        None => mac.to_string(),
+    }
+}
+
+pub fn format_ip(ip: IpAddr, zone: Option<&Zone>) -> String {
+    match zone {
+        Some(zone) => format!("{}%{}", ip, zone),
+        None => ip.to_string(),
+    }
+}
+
+pub fn format_host(ip: IpAddr, zone: Option<&Zone>, hostname: Option<&str>) -> String {
+    match hostname {
+        Some(hostname) => format!("{} ({})", hostname, format_ip(ip, zone)),
+        None => format_ip(ip, zone),
+    }
+}
+
+pub fn format_endpoint(ip: IpAddr, zone: Option<&Zone>, hostname: Option<&str>, port: u16) -> String {
+    match hostname {
+        None if ip.is_ipv6() => format!("[{}]:{}", format_ip(ip, zone), port),
+        _ => format!("{}:{}", format_host(ip, zone, hostname), port),
+    }
+}